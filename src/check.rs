@@ -0,0 +1,165 @@
+use bumpalo::Bump;
+use std::fs;
+use std::net::ToSocketAddrs;
+use std::panic;
+
+use crate::structs::metadata::Metadata;
+use crate::util::head;
+use crate::util::request::fetch;
+use crate::util::variables::{CHECK_URL, FFMPEG, FFPROBE, HOST, MEDIA_CACHE_DIR};
+
+// A page covering the metadata every embed relies on.
+const FIXTURE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <base href="https://example.com/blog/">
+    <title>Fallback title</title>
+    <meta property="og:title" content="January">
+    <meta name="description" content="Embeds and proxies for Revolt.">
+    <meta property="og:image" content="preview.png">
+    <meta property="og:image:width" content="1200">
+    <meta property="og:image:height" content="630">
+    <meta name="theme-color" content="rgb(255, 0, 0)">
+</head>
+</html>"#;
+
+/// Configuration which would otherwise only fail once it's first used.
+fn config() -> Result<String, String> {
+    let result = panic::catch_unwind(|| {
+        lazy_static::initialize(&HOST);
+        crate::warm();
+    });
+
+    result
+        .map(|_| format!("listening on {}", *HOST))
+        .map_err(|panic| {
+            panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "invalid configuration".to_string())
+        })
+}
+
+fn dns() -> Result<String, String> {
+    let url = reqwest::Url::parse(&CHECK_URL).map_err(|error| error.to_string())?;
+    let host = url.host_str().ok_or("check URL has no host")?;
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addresses = (host, port)
+        .to_socket_addrs()
+        .map_err(|error| format!("could not resolve {}: {}", host, error))?;
+    Ok(format!(
+        "{} resolves to {} addresses",
+        host,
+        addresses.count()
+    ))
+}
+
+/// Fetches go through any proxy set in the environment, as embeds do.
+async fn egress() -> Result<String, String> {
+    fetch(&CHECK_URL)
+        .await
+        .map(|(_, mime)| format!("fetched {} ({})", *CHECK_URL, mime))
+        .map_err(|error| format!("could not fetch {}: {:?}", *CHECK_URL, error))
+}
+
+/// Embeds and proxied images are cached in memory, only transcodes are
+/// kept on disk, so that's the one backend which can be misconfigured.
+fn cache() -> Result<String, String> {
+    if FFMPEG.is_none() {
+        return Ok("in memory".to_string());
+    }
+
+    let probe = MEDIA_CACHE_DIR.join(".check");
+    fs::create_dir_all(&*MEDIA_CACHE_DIR)
+        .and_then(|_| fs::write(&probe, b"january"))
+        .and_then(|_| fs::remove_file(&probe))
+        .map(|_| format!("{} is writable", MEDIA_CACHE_DIR.display()))
+        .map_err(|error| format!("{} is not writable: {}", MEDIA_CACHE_DIR.display(), error))
+}
+
+fn tools() -> Result<String, String> {
+    let mut found = Vec::new();
+    for (name, path) in [("ffmpeg", &*FFMPEG), ("ffprobe", &*FFPROBE)] {
+        if let Some(path) = path {
+            if fs::metadata(path).is_err() {
+                return Err(format!("{} not found at {}", name, path));
+            }
+
+            found.push(name);
+        }
+    }
+
+    if found.is_empty() {
+        Ok("none configured".to_string())
+    } else {
+        Ok(found.join(", "))
+    }
+}
+
+fn canary() -> Result<String, String> {
+    let arena = Bump::new();
+    let document = head::scan(FIXTURE, &arena);
+    let metadata =
+        Metadata::from_document(&document, "https://example.com/blog/post".to_string(), None)
+            .map_err(|error| format!("{:?}", error))?;
+    let embed = serde_json::to_value(&metadata).map_err(|error| error.to_string())?;
+
+    let expected = [
+        ("/title", "January"),
+        ("/description", "Embeds and proxies for Revolt."),
+        ("/image/url", "https://example.com/blog/preview.png"),
+        ("/colour", "#ff0000"),
+    ];
+
+    for (pointer, value) in expected.iter() {
+        if embed.pointer(pointer).and_then(|v| v.as_str()) != Some(*value) {
+            return Err(format!(
+                "expected {} to be {:?}, got {}",
+                pointer, value, embed
+            ));
+        }
+    }
+
+    Ok("fixture embedded as expected".to_string())
+}
+
+fn report(name: &str, result: Result<String, String>) -> bool {
+    match &result {
+        Ok(detail) => println!("ok   {:<8} {}", name, detail),
+        Err(detail) => println!("FAIL {:<8} {}", name, detail),
+    }
+
+    result.is_ok()
+}
+
+/// Run every check for `january check`, returning the exit code.
+pub async fn run() -> i32 {
+    // Panics are reported as failed checks instead.
+    panic::set_hook(Box::new(|_| {}));
+
+    let configured = report("config", config());
+
+    // Parsing reads the configuration, which would just panic again.
+    let canary = if configured {
+        canary()
+    } else {
+        Err("skipped, the configuration is invalid".to_string())
+    };
+
+    let results = [
+        configured,
+        report("dns", dns()),
+        report("egress", egress().await),
+        report("cache", cache()),
+        report("tools", tools()),
+        report("canary", canary),
+    ];
+
+    if results.iter().all(|ok| *ok) {
+        0
+    } else {
+        1
+    }
+}
@@ -1,29 +1,184 @@
 #[macro_use]
 extern crate lazy_static;
 
-use actix_web::middleware::Logger;
-use actix_web::{web, App, HttpServer};
-use log::info;
-use util::variables::HOST;
+use actix_web::dev::Service;
+use actix_web::middleware::{Compress, Logger};
+use actix_web::{web, App, HttpServer, ResponseError};
+use log::{info, warn};
+use util::{
+    quota, request,
+    result::Error,
+    trace,
+    variables::{
+        ADMIN_HOST, COMPRESSION, FACEBOOK_TOKEN, FFMPEG, HOST, IFRAME_PROVIDERS, IMGUR_CLIENT_ID,
+        META_PRECEDENCE, OPENWEATHERMAP_KEY, REVOLT_APP_URL, SONGLINK_RESOLVE, STATIC_MAP_URL,
+        STOCK_EMBEDS, TRACE_HEADER, WAYBACK_FALLBACK,
+    },
+};
 
+pub mod check;
+pub mod providers;
 pub mod routes;
+pub mod snapshot;
 pub mod structs;
 pub mod util;
 
+/// Operational endpoints, kept off the public listener when an admin address is set.
+fn admin(cfg: &mut web::ServiceConfig) {
+    cfg.route("/prewarm", web::post().to(routes::prewarm::post))
+        .route("/bandwidth", web::get().to(routes::admin::bandwidth))
+        .route("/metrics", web::get().to(routes::admin::metrics));
+}
+
+/// Compile patterns and read configuration up front, so the first embed
+/// doesn't pay for it and mistakes are caught before serving anything.
+fn warm() {
+    let patterns = structs::metadata::warm()
+        + providers::bluesky::warm()
+        + providers::drive::warm()
+        + providers::facebook::warm()
+        + providers::github::warm()
+        + providers::imgur::warm()
+        + providers::mastodon::warm()
+        + providers::photo::warm()
+        + providers::reddit::warm()
+        + providers::revolt::warm()
+        + providers::slides::warm()
+        + providers::steam::warm()
+        + providers::twitter::warm()
+        + providers::wikipedia::warm()
+        + structs::route::warm()
+        + routes::scan::warm();
+    request::warm();
+    let checks = util::reputation::warm();
+    lazy_static::initialize(&META_PRECEDENCE);
+
+    info!(
+        "Compiled {} patterns and {} iframe providers.",
+        patterns,
+        IFRAME_PROVIDERS.len()
+    );
+    info!("Loaded {} reputation checks.", checks);
+
+    let faults = util::fault::warm();
+    if faults > 0.0 {
+        warn!(
+            "Injecting failures into {}% of upstream requests.",
+            faults * 100.0
+        );
+    }
+
+    // Providers which only work once configured.
+    let optional = [
+        ("Revolt", REVOLT_APP_URL.is_some()),
+        ("Facebook", FACEBOOK_TOKEN.is_some()),
+        ("Imgur album counts", IMGUR_CLIENT_ID.is_some()),
+        ("OpenWeatherMap", OPENWEATHERMAP_KEY.is_some()),
+        ("Yahoo Finance", *STOCK_EMBEDS),
+        ("song.link", *SONGLINK_RESOLVE),
+        ("Wayback Machine", *WAYBACK_FALLBACK),
+        ("static maps", STATIC_MAP_URL.is_some()),
+        ("ffmpeg", FFMPEG.is_some()),
+        ("HEIC", cfg!(feature = "heic")),
+        ("JPEG XL", cfg!(feature = "jxl")),
+    ];
+
+    let names = |enabled: bool| {
+        let names: Vec<_> = optional
+            .iter()
+            .filter(|(_, on)| *on == enabled)
+            .map(|(name, _)| *name)
+            .collect();
+
+        if names.is_empty() {
+            "none".to_string()
+        } else {
+            names.join(", ")
+        }
+    };
+
+    info!("Enabled optional providers: {}", names(true));
+    info!("Disabled optional providers: {}", names(false));
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     env_logger::init_from_env(env_logger::Env::default().filter_or("RUST_LOG", "info"));
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("check") => std::process::exit(check::run().await),
+        Some("snapshot") => std::process::exit(match args.get(1).map(String::as_str) {
+            Some("--record") => snapshot::record(&args[2..]).await,
+            _ => snapshot::run(&args[1..]),
+        }),
+        _ => {}
+    }
+
     info!("Starting January server.");
+    warm();
 
-    HttpServer::new(|| {
+    let public = HttpServer::new(|| {
         App::new()
+            .wrap_fn(|req, srv| {
+                let upstream = matches!(req.path(), "/embed" | "/proxy" | "/scan" | "/thumbnail");
+                let usage = if upstream { quota::consume(&req) } else { None };
+
+                // Rejections still carry the headers so clients know when to come back.
+                let exceeded = usage.as_ref().map(|u| u.exceeded()).unwrap_or(false);
+                let res = if exceeded {
+                    Err(req.into_response(Error::QuotaExceeded.error_response()))
+                } else {
+                    let header = req
+                        .headers()
+                        .get(TRACE_HEADER.as_str())
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string);
+                    Ok(trace::scope(header, srv.call(req)))
+                };
+
+                async move {
+                    let mut res = match res {
+                        Ok(fut) => fut.await?,
+                        Err(res) => res,
+                    };
+
+                    if let Some(usage) = usage {
+                        usage.apply(res.headers_mut());
+                    }
+
+                    Ok(res)
+                }
+            })
+            .wrap(Compress::new(*COMPRESSION))
             .wrap(Logger::default())
             .route("/", web::get().to(routes::info::get))
+            .route("/config", web::get().to(routes::config::get))
             .route("/embed", web::get().to(routes::embed::get))
             .route("/proxy", web::get().to(routes::proxy::get))
+            .route("/scan", web::post().to(routes::scan::post))
+            .configure(|cfg| {
+                if FFMPEG.is_some() {
+                    cfg.route("/thumbnail", web::get().to(routes::thumbnail::get));
+                }
+            })
+            .configure(|cfg| {
+                if ADMIN_HOST.is_none() {
+                    admin(cfg);
+                }
+            })
     })
     .bind(HOST.clone())?
-    .run()
-    .await
+    .run();
+
+    match ADMIN_HOST.as_ref() {
+        Some(host) => {
+            let admin = HttpServer::new(|| App::new().wrap(Logger::default()).configure(admin))
+                .bind(host)?
+                .run();
+
+            futures::try_join!(public, admin).map(|_| ())
+        }
+        None => public.await,
+    }
 }
@@ -0,0 +1,86 @@
+use scraper::Html;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::structs::special::Special;
+use crate::util::request::{consume_json, fetch_with_header};
+
+pub const ACTIVITY_JSON: &str = "application/activity+json";
+
+#[derive(Deserialize)]
+struct Actor {
+    #[serde(rename = "preferredUsername")]
+    username: Option<String>,
+    name: Option<String>,
+    icon: Option<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Object {
+    id: String,
+    attributed_to: Option<Value>,
+    content: Option<String>,
+    summary: Option<String>,
+    published: Option<String>,
+    #[serde(default)]
+    sensitive: bool,
+    #[serde(default)]
+    attachment: Vec<Value>,
+}
+
+/// Pull a URL out of a field which may be a plain string,
+/// an object with a `url` / `id` or a list of either.
+fn url_of(value: &Value) -> Option<String> {
+    match value {
+        Value::String(url) => Some(url.clone()),
+        Value::Array(values) => values.iter().find_map(url_of),
+        Value::Object(object) => object
+            .get("url")
+            .or_else(|| object.get("href"))
+            .or_else(|| object.get("id"))
+            .and_then(url_of),
+        _ => None,
+    }
+}
+
+/// Plain text of some HTML content.
+pub fn text_of(html: &str) -> String {
+    Html::parse_fragment(html)
+        .root_element()
+        .text()
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Fetch an ActivityPub object, along with its author, and build a special from it.
+pub async fn lookup(url: &str) -> Option<Special> {
+    let (resp, _) = fetch_with_header(url, "Accept", ACTIVITY_JSON).await.ok()?;
+    let object: Object = consume_json(resp).await.ok()?;
+
+    let author_url = object.attributed_to.as_ref().and_then(url_of);
+    let mut author = None;
+    if let Some(author_url) = &author_url {
+        if let Ok((resp, _)) = fetch_with_header(author_url, "Accept", ACTIVITY_JSON).await {
+            author = consume_json::<Actor>(resp).await.ok();
+        }
+    }
+
+    Some(Special::Fediverse {
+        id: object.id,
+        author_url,
+        author_name: author
+            .as_ref()
+            .and_then(|actor| actor.name.clone().or_else(|| actor.username.clone())),
+        author_avatar: author
+            .as_ref()
+            .and_then(|actor| actor.icon.as_ref())
+            .and_then(url_of),
+        content: object.content.as_deref().map(text_of),
+        content_warning: object.summary.filter(|summary| !summary.is_empty()),
+        published: object.published,
+        sensitive: object.sensitive,
+        attachments: object.attachment.iter().filter_map(url_of).collect(),
+    })
+}
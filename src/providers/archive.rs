@@ -0,0 +1,49 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::structs::special::Special;
+use crate::util::request::{consume_json, fetch};
+
+#[derive(Deserialize)]
+struct Item {
+    metadata: Option<ItemMetadata>,
+}
+
+#[derive(Deserialize)]
+struct ItemMetadata {
+    title: Option<Value>,
+    mediatype: Option<String>,
+    creator: Option<Value>,
+}
+
+/// Metadata fields may be either a single value or a list of them.
+fn first(value: Option<Value>) -> Option<String> {
+    match value? {
+        Value::String(value) => Some(value),
+        Value::Array(values) => values.into_iter().find_map(|v| first(Some(v))),
+        _ => None,
+    }
+}
+
+/// Look up an item through the Internet Archive metadata API.
+pub async fn item(id: &str) -> Option<Special> {
+    let url = format!("https://archive.org/metadata/{}", id);
+    let (resp, _) = fetch(&url).await.ok()?;
+    let metadata = consume_json::<Item>(resp).await.ok()?.metadata?;
+
+    // Only these media types have an embeddable player (or book reader).
+    let player = match metadata.mediatype.as_deref() {
+        Some("audio") | Some("etree") | Some("movies") | Some("texts") => {
+            Some(format!("https://archive.org/embed/{}", id))
+        }
+        _ => None,
+    };
+
+    Some(Special::InternetArchive {
+        id: id.to_string(),
+        title: first(metadata.title),
+        creator: first(metadata.creator),
+        media_type: metadata.mediatype,
+        player,
+    })
+}
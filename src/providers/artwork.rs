@@ -0,0 +1,73 @@
+use serde::Deserialize;
+
+use crate::structs::special::{ArtworkProvider, Special};
+use crate::util::request::{consume_json, fetch};
+
+#[derive(Deserialize)]
+struct DeviantArtOEmbed {
+    title: Option<String>,
+    author_name: Option<String>,
+    url: Option<String>,
+    thumbnail_url: Option<String>,
+    safety: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ArtStationUser {
+    full_name: Option<String>,
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct ArtStationAsset {
+    asset_type: String,
+    image_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ArtStationProject {
+    title: Option<String>,
+    user: ArtStationUser,
+    #[serde(default)]
+    assets: Vec<ArtStationAsset>,
+    cover_url: Option<String>,
+    #[serde(default)]
+    adult_content: bool,
+}
+
+/// Look up a deviation through DeviantArt's oEmbed endpoint.
+pub async fn deviantart(url: &str) -> Option<Special> {
+    let mut api = reqwest::Url::parse("https://backend.deviantart.com/oembed").ok()?;
+    api.query_pairs_mut().append_pair("url", url);
+
+    let (resp, _) = fetch(api.as_str()).await.ok()?;
+    let oembed: DeviantArtOEmbed = consume_json(resp).await.ok()?;
+
+    Some(Special::Artwork {
+        provider: ArtworkProvider::DeviantArt,
+        title: oembed.title,
+        author: oembed.author_name,
+        image: oembed.url.or(oembed.thumbnail_url),
+        mature: oembed.safety.map(|s| s != "nonadult").unwrap_or(false),
+    })
+}
+
+/// Look up a project through ArtStation's public project JSON.
+pub async fn artstation(hash: &str) -> Option<Special> {
+    let url = format!("https://www.artstation.com/projects/{}.json", hash);
+    let (resp, _) = fetch(&url).await.ok()?;
+    let project: ArtStationProject = consume_json(resp).await.ok()?;
+
+    Some(Special::Artwork {
+        provider: ArtworkProvider::ArtStation,
+        title: project.title,
+        author: Some(project.user.full_name.unwrap_or(project.user.username)),
+        image: project
+            .assets
+            .into_iter()
+            .find(|asset| asset.asset_type == "image")
+            .and_then(|asset| asset.image_url)
+            .or(project.cover_url),
+        mature: project.adult_content,
+    })
+}
@@ -0,0 +1,132 @@
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::structs::special::Special;
+use crate::util::request::{consume_json, fetch};
+
+const APPVIEW: &str = "https://public.api.bsky.app/xrpc";
+
+lazy_static! {
+    static ref RE_POST: Regex =
+        Regex::new("^(?:https?://)?(?:www\\.)?bsky\\.app/profile/([\\w.:%-]+)/post/([\\w~.-]+)")
+            .unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_POST];
+    patterns.len()
+}
+
+#[derive(Deserialize)]
+struct Resolved {
+    did: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Author {
+    handle: String,
+    display_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Post {
+    author: Author,
+    record: Value,
+    embed: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct Posts {
+    posts: Vec<Post>,
+}
+
+pub fn is_post(url: &str) -> bool {
+    RE_POST.is_match(url)
+}
+
+/// Build a special for a post, filled in from the public AppView.
+pub async fn lookup(url: &str) -> Option<Special> {
+    let captures = RE_POST.captures(url)?;
+    let rkey = captures[2].to_string();
+
+    // Links usually name the author by handle, but records are keyed by DID.
+    let actor = &captures[1];
+    let did = if actor.starts_with("did:") {
+        actor.to_string()
+    } else {
+        resolve_handle(actor).await?
+    };
+
+    let post = post(&did, &rkey).await;
+    let (handle, author_name, text, images) = match post {
+        Some(post) => (
+            Some(post.author.handle),
+            post.author.display_name.filter(|name| !name.is_empty()),
+            post.record["text"]
+                .as_str()
+                .filter(|text| !text.is_empty())
+                .map(str::to_string),
+            post.embed.as_ref().map(images_of).unwrap_or_default(),
+        ),
+        None => (None, None, None, Vec::new()),
+    };
+
+    Some(Special::Bluesky {
+        did,
+        rkey,
+        handle,
+        author_name,
+        text,
+        images,
+    })
+}
+
+/// Full size image URLs from an embed view, including
+/// those attached alongside a quoted post.
+fn images_of(embed: &Value) -> Vec<String> {
+    let images = match embed["$type"].as_str() {
+        Some("app.bsky.embed.images#view") => &embed["images"],
+        Some("app.bsky.embed.recordWithMedia#view") => &embed["media"]["images"],
+        _ => return Vec::new(),
+    };
+
+    images
+        .as_array()
+        .map(|images| {
+            images
+                .iter()
+                .filter_map(|image| image["fullsize"].as_str())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn resolve_handle(handle: &str) -> Option<String> {
+    let mut api =
+        reqwest::Url::parse(&format!("{}/com.atproto.identity.resolveHandle", APPVIEW)).ok()?;
+    api.query_pairs_mut().append_pair("handle", handle);
+
+    let (resp, _) = fetch(api.as_str()).await.ok()?;
+    consume_json::<Resolved>(resp)
+        .await
+        .ok()
+        .map(|resolved| resolved.did)
+}
+
+async fn post(did: &str, rkey: &str) -> Option<Post> {
+    let mut api = reqwest::Url::parse(&format!("{}/app.bsky.feed.getPosts", APPVIEW)).ok()?;
+    api.query_pairs_mut()
+        .append_pair("uris", &format!("at://{}/app.bsky.feed.post/{}", did, rkey));
+
+    let (resp, _) = fetch(api.as_str()).await.ok()?;
+    consume_json::<Posts>(resp)
+        .await
+        .ok()?
+        .posts
+        .into_iter()
+        .next()
+}
@@ -0,0 +1,91 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::structs::special::{CampaignProvider, Special};
+use crate::util::request::{consume_json, fetch};
+
+#[derive(Deserialize)]
+struct KickstarterStats {
+    project: KickstarterProject,
+}
+
+#[derive(Deserialize)]
+struct KickstarterProject {
+    state: Option<String>,
+    backers_count: Option<u64>,
+    pledged: Option<Value>,
+}
+
+/// Look up live funding numbers for a Kickstarter project.
+pub async fn kickstarter(project: &str, goal: Option<f64>) -> Option<Special> {
+    let url = format!(
+        "https://www.kickstarter.com/projects/{}/stats.json?v=1",
+        project
+    );
+
+    let (resp, _) = fetch(&url).await.ok()?;
+    let stats: KickstarterStats = consume_json(resp).await.ok()?;
+
+    Some(Special::Campaign {
+        provider: CampaignProvider::Kickstarter,
+        supporters: stats.project.backers_count,
+        // Pledged amounts are given as a decimal string.
+        pledged: stats.project.pledged.and_then(|pledged| match pledged {
+            Value::String(pledged) => pledged.parse().ok(),
+            pledged => pledged.as_f64(),
+        }),
+        goal,
+        currency: None,
+        state: stats.project.state,
+    })
+}
+
+#[derive(Deserialize)]
+struct PatreonCampaigns {
+    data: Vec<PatreonCampaign>,
+}
+
+#[derive(Deserialize)]
+struct PatreonCampaign {
+    attributes: PatreonAttributes,
+}
+
+#[derive(Deserialize)]
+struct PatreonAttributes {
+    patron_count: Option<u64>,
+    pledge_sum: Option<f64>,
+    currency: Option<String>,
+    is_monthly: Option<bool>,
+}
+
+/// Look up a creator's campaign through Patreon's public API.
+pub async fn patreon(vanity: &str) -> Option<Special> {
+    let mut api = reqwest::Url::parse("https://www.patreon.com/api/campaigns").ok()?;
+    api.query_pairs_mut()
+        .append_pair("filter[vanity]", vanity)
+        .append_pair(
+            "fields[campaign]",
+            "patron_count,pledge_sum,currency,is_monthly",
+        );
+
+    let (resp, _) = fetch(api.as_str()).await.ok()?;
+    let campaign = consume_json::<PatreonCampaigns>(resp)
+        .await
+        .ok()?
+        .data
+        .into_iter()
+        .next()?
+        .attributes;
+
+    Some(Special::Campaign {
+        provider: CampaignProvider::Patreon,
+        supporters: campaign.patron_count,
+        // Patreon reports amounts in cents.
+        pledged: campaign.pledge_sum.map(|sum| sum / 100.0),
+        goal: None,
+        currency: campaign.currency,
+        state: campaign
+            .is_monthly
+            .map(|monthly| if monthly { "monthly" } else { "per_creation" }.to_string()),
+    })
+}
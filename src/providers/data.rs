@@ -0,0 +1,96 @@
+use serde::Deserialize;
+
+use crate::structs::special::Special;
+use crate::util::request::{consume_json, fetch};
+use crate::util::variables::OPENWEATHERMAP_KEY;
+
+#[derive(Deserialize)]
+struct Chart {
+    chart: ChartResults,
+}
+
+#[derive(Deserialize)]
+struct ChartResults {
+    result: Option<Vec<ChartResult>>,
+}
+
+#[derive(Deserialize)]
+struct ChartResult {
+    meta: ChartMeta,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChartMeta {
+    symbol: String,
+    currency: Option<String>,
+    regular_market_price: Option<f64>,
+    chart_previous_close: Option<f64>,
+}
+
+/// Look up the latest price of a ticker through Yahoo Finance.
+pub async fn stock(symbol: &str) -> Option<Special> {
+    let url = format!(
+        "https://query1.finance.yahoo.com/v8/finance/chart/{}?range=1d&interval=1d",
+        symbol
+    );
+
+    let (resp, _) = fetch(&url).await.ok()?;
+    let meta = consume_json::<Chart>(resp)
+        .await
+        .ok()?
+        .chart
+        .result?
+        .into_iter()
+        .next()?
+        .meta;
+
+    let price = meta.regular_market_price?;
+    Some(Special::Stock {
+        symbol: meta.symbol,
+        price,
+        currency: meta.currency,
+        change: meta.chart_previous_close.map(|close| price - close),
+    })
+}
+
+#[derive(Deserialize)]
+struct Weather {
+    name: String,
+    main: WeatherMain,
+    #[serde(default)]
+    weather: Vec<WeatherCondition>,
+}
+
+#[derive(Deserialize)]
+struct WeatherMain {
+    temp: f64,
+}
+
+#[derive(Deserialize)]
+struct WeatherCondition {
+    description: String,
+    icon: String,
+}
+
+/// Look up current conditions for an OpenWeatherMap city, if we have a key.
+pub async fn weather(city: &str) -> Option<Special> {
+    let key = OPENWEATHERMAP_KEY.as_ref()?;
+    let url = format!(
+        "https://api.openweathermap.org/data/2.5/weather?id={}&appid={}&units=metric",
+        city, key
+    );
+
+    let (resp, _) = fetch(&url).await.ok()?;
+    let weather: Weather = consume_json(resp).await.ok()?;
+    let condition = weather.weather.into_iter().next();
+
+    Some(Special::Weather {
+        city: weather.name,
+        temperature: weather.main.temp,
+        icon: condition
+            .as_ref()
+            .map(|c| format!("https://openweathermap.org/img/wn/{}@2x.png", c.icon)),
+        description: condition.map(|c| c.description),
+    })
+}
@@ -0,0 +1,43 @@
+use serde::Deserialize;
+
+use crate::structs::special::Special;
+use crate::util::request::{consume_json, fetch};
+
+#[derive(Deserialize)]
+struct Guild {
+    id: String,
+    name: String,
+    icon: Option<String>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Invite {
+    guild: Option<Guild>,
+    approximate_member_count: Option<usize>,
+    approximate_presence_count: Option<usize>,
+}
+
+/// Resolve an invite code through Discord's public invite API.
+pub async fn lookup(code: &str) -> Option<Special> {
+    let url = format!(
+        "https://discord.com/api/v9/invites/{}?with_counts=true",
+        code
+    );
+
+    let (resp, _) = fetch(&url).await.ok()?;
+    let invite: Invite = consume_json(resp).await.ok()?;
+    let guild = invite.guild?;
+
+    Some(Special::DiscordInvite {
+        code: code.to_string(),
+        icon: guild
+            .icon
+            .as_ref()
+            .map(|icon| format!("https://cdn.discordapp.com/icons/{}/{}.png", guild.id, icon)),
+        name: guild.name,
+        description: guild.description,
+        members: invite.approximate_member_count,
+        online: invite.approximate_presence_count,
+    })
+}
@@ -0,0 +1,132 @@
+use regex::Regex;
+use reqwest::header::{CONTENT_DISPOSITION, CONTENT_LENGTH};
+use reqwest::{Response, Url};
+use serde::Deserialize;
+
+use crate::structs::special::{FileProvider, Special};
+use crate::util::request::{consume_json, fetch, fetch_head};
+
+lazy_static! {
+    static ref RE_FILENAME: Regex = Regex::new("filename=\"?([^\";]+)\"?").unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_FILENAME];
+    patterns.len()
+}
+
+fn content_length(resp: &Response) -> Option<u64> {
+    resp.headers()
+        .get(CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+fn content_disposition_name(resp: &Response) -> Option<String> {
+    let disposition = resp.headers().get(CONTENT_DISPOSITION)?.to_str().ok()?;
+    Some(RE_FILENAME.captures(disposition)?[1].to_string())
+}
+
+/// Look up a Dropbox share by asking for the direct download's headers.
+pub async fn dropbox(url: &str, name: &str) -> Option<Special> {
+    let mut download = Url::parse(url).ok()?;
+    let pairs: Vec<(String, String)> = download
+        .query_pairs()
+        .filter(|(key, _)| key != "dl" && key != "raw")
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    download
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("raw", "1");
+
+    let (resp, mime) = fetch_head(download.as_str()).await.ok()?;
+    let name = content_disposition_name(&resp).unwrap_or_else(|| {
+        percent_encoding::percent_decode_str(name)
+            .decode_utf8_lossy()
+            .to_string()
+    });
+
+    Some(Special::File {
+        provider: FileProvider::Dropbox,
+        name,
+        size: content_length(&resp),
+        thumbnail: Some(download.to_string()).filter(|_| mime.type_() == mime::IMAGE),
+        mime: Some(mime.essence_str().to_string()),
+    })
+}
+
+/// Look up a Google Drive file, the title is taken from the share page.
+pub async fn google_drive(id: &str, title: Option<&str>) -> Option<Special> {
+    let download = format!("https://drive.google.com/uc?export=download&id={}", id);
+
+    // Large files are served behind a virus scan warning page
+    // instead, in which case we know nothing about the file.
+    let (resp, mime) = fetch_head(&download).await.ok()?;
+    let known = mime.subtype() != mime::HTML;
+
+    Some(Special::File {
+        provider: FileProvider::GoogleDrive,
+        name: content_disposition_name(&resp)
+            .or_else(|| title.map(|t| t.trim_end_matches(" - Google Drive").to_string()))?,
+        mime: Some(mime.essence_str().to_string()).filter(|_| known),
+        size: content_length(&resp).filter(|_| known),
+        thumbnail: Some(format!(
+            "https://drive.google.com/thumbnail?id={}&sz=w1000",
+            id
+        )),
+    })
+}
+
+#[derive(Deserialize)]
+struct DriveItem {
+    name: String,
+    size: Option<u64>,
+    file: Option<DriveFile>,
+    #[serde(default)]
+    thumbnails: Vec<ThumbnailSet>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DriveFile {
+    mime_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ThumbnailSet {
+    large: Option<Thumbnail>,
+}
+
+#[derive(Deserialize)]
+struct Thumbnail {
+    url: String,
+}
+
+/// Look up a OneDrive share through the public shares API.
+pub async fn onedrive(url: &str) -> Option<Special> {
+    let share = base64::encode_config(url, base64::URL_SAFE_NO_PAD);
+    let api = format!(
+        "https://api.onedrive.com/v1.0/shares/u!{}/root?expand=thumbnails",
+        share
+    );
+
+    let (resp, _) = fetch(&api).await.ok()?;
+    let item: DriveItem = consume_json(resp).await.ok()?;
+
+    Some(Special::File {
+        provider: FileProvider::OneDrive,
+        name: item.name,
+        mime: item.file.and_then(|file| file.mime_type),
+        size: item.size,
+        thumbnail: item
+            .thumbnails
+            .into_iter()
+            .find_map(|set| set.large)
+            .map(|thumbnail| thumbnail.url),
+    })
+}
@@ -0,0 +1,37 @@
+use regex::Regex;
+
+use crate::structs::special::{FacebookType, Special};
+
+// Facebook only serves OpenGraph tags to its own crawler.
+pub const USER_AGENT: &str = "facebookexternalhit/1.1";
+
+lazy_static! {
+    static ref RE_FACEBOOK: Regex =
+        Regex::new("^(?:https?://)?(?:[\\w-]+\\.)?(?:facebook\\.com|fb\\.watch)/").unwrap();
+    static ref RE_VIDEO: Regex = Regex::new("^(?:https?://)?(?:www\\.|m\\.|web\\.)?facebook\\.com/(?:watch/?\\?(?:\\S*&)?v=|[\\w.]+/videos/(?:[\\w-]+/)?|reel/)(\\d+)").unwrap();
+    static ref RE_POST: Regex = Regex::new("^(?:https?://)?(?:www\\.|m\\.|web\\.)?facebook\\.com/(?:[\\w.]+/posts/|permalink\\.php\\?(?:\\S*&)?story_fbid=)(\\w+)").unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_FACEBOOK, &RE_VIDEO, &RE_POST];
+    patterns.len()
+}
+
+pub fn is_facebook(url: &str) -> bool {
+    RE_FACEBOOK.is_match(url)
+}
+
+/// Build a special for a video or post from its link alone, as the
+/// page is often a login wall by the time we get to it.
+pub fn special(url: &str) -> Option<Special> {
+    let (content_type, captures) = match RE_VIDEO.captures(url) {
+        Some(captures) => (FacebookType::Video, captures),
+        None => (FacebookType::Post, RE_POST.captures(url)?),
+    };
+
+    Some(Special::Facebook {
+        content_type,
+        id: captures[1].to_string(),
+    })
+}
@@ -0,0 +1,26 @@
+use reqwest::Url;
+
+use crate::util::variables::{INVIDIOUS_HOSTS, LIBREDDIT_HOSTS, NITTER_HOSTS};
+
+/// Map links to privacy front-ends onto the site they mirror, if any.
+///
+/// Each of these keeps the original site's paths, so only the host changes.
+pub fn normalise(url: &str) -> Option<String> {
+    let mut url = Url::parse(url).ok()?;
+    let host = url.host_str()?.to_lowercase();
+
+    let canonical = if NITTER_HOSTS.contains(&host) {
+        "twitter.com"
+    } else if INVIDIOUS_HOSTS.contains(&host) {
+        "www.youtube.com"
+    } else if LIBREDDIT_HOSTS.contains(&host) {
+        "www.reddit.com"
+    } else {
+        return None;
+    };
+
+    url.set_host(Some(canonical)).ok()?;
+    url.set_port(None).ok()?;
+    url.set_scheme("https").ok()?;
+    Some(url.to_string())
+}
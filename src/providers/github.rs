@@ -0,0 +1,131 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::structs::special::{GitHubType, Special};
+use crate::util::request::{consume_json, fetch, fetch_with_header};
+use crate::util::variables::GITHUB_TOKEN;
+
+lazy_static! {
+    static ref RE_REPOSITORY: Regex = Regex::new(
+        "^(?:https?://)?(?:www\\.)?github\\.com/([\\w.-]+)/([\\w.-]+?)(?:\\.git)?(?:/(issues|pull)/(\\d+))?/?(?:$|[/?#])"
+    )
+    .unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_REPOSITORY];
+    patterns.len()
+}
+
+// Top level pages which look like an owner but aren't one.
+const RESERVED: &[&str] = &[
+    "about",
+    "apps",
+    "collections",
+    "enterprise",
+    "events",
+    "explore",
+    "features",
+    "login",
+    "marketplace",
+    "notifications",
+    "orgs",
+    "pricing",
+    "settings",
+    "sponsors",
+    "topics",
+    "trending",
+];
+
+#[derive(Deserialize)]
+struct Repository {
+    description: Option<String>,
+    language: Option<String>,
+    stargazers_count: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct Issue {
+    title: Option<String>,
+    state: Option<String>,
+}
+
+pub fn is_repository(url: &str) -> bool {
+    RE_REPOSITORY
+        .captures(url)
+        .map(|captures| !RESERVED.contains(&&captures[1]))
+        .unwrap_or(false)
+}
+
+async fn api<T: serde::de::DeserializeOwned>(path: &str) -> Option<T> {
+    let url = format!("https://api.github.com/{}", path);
+    let (resp, _) = match GITHUB_TOKEN.as_ref() {
+        Some(token) => fetch_with_header(&url, "Authorization", &format!("token {}", token)).await,
+        None => fetch(&url).await,
+    }
+    .ok()?;
+
+    consume_json(resp).await.ok()
+}
+
+/// Build a special for a repository, issue or pull request, filled in
+/// from the REST API. Without a token it allows 60 lookups an hour, after
+/// which only what the link says is returned.
+pub async fn lookup(url: &str) -> Option<Special> {
+    let captures = RE_REPOSITORY.captures(url)?;
+    let (owner, repo) = (captures[1].to_string(), captures[2].to_string());
+    let number = captures.get(4).and_then(|m| m.as_str().parse().ok());
+    let content_type = match captures.get(3).map(|m| m.as_str()) {
+        Some("issues") => GitHubType::Issue,
+        Some(_) => GitHubType::PullRequest,
+        None => GitHubType::Repository,
+    };
+
+    let repository: Option<Repository> = api(&format!("repos/{}/{}", owner, repo)).await;
+    let issue: Option<Issue> = match number {
+        Some(number) => api(&format!("repos/{}/{}/issues/{}", owner, repo, number)).await,
+        None => None,
+    };
+
+    let (description, language, stars) = match repository {
+        Some(repository) => (
+            repository.description.filter(|text| !text.is_empty()),
+            repository.language,
+            repository.stargazers_count,
+        ),
+        None => (None, None, None),
+    };
+
+    let (title, state) = match issue {
+        Some(issue) => (issue.title, issue.state),
+        None => (None, None),
+    };
+
+    Some(Special::GitHub {
+        content_type,
+        owner: Some(owner),
+        repo: Some(repo),
+        id: number.map(|number: u64| number.to_string()),
+        title,
+        state,
+        description,
+        language,
+        stars,
+    })
+}
+
+/// Special for a gist which couldn't be previewed as a paste.
+pub fn gist(owner: Option<String>, id: &str) -> Special {
+    Special::GitHub {
+        content_type: GitHubType::Gist,
+        owner,
+        repo: None,
+        id: Some(id.to_string()),
+        title: None,
+        state: None,
+        description: None,
+        language: None,
+        stars: None,
+    }
+}
@@ -0,0 +1,82 @@
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Deserialize;
+
+use crate::structs::special::Special;
+use crate::util::request::{consume_json, fetch};
+
+// Top level pages on gitlab.com which look like a group but aren't one.
+const RESERVED: &[&str] = &[
+    "dashboard",
+    "explore",
+    "groups",
+    "help",
+    "projects",
+    "search",
+    "users",
+];
+
+#[derive(Deserialize)]
+struct Project {
+    path_with_namespace: String,
+    default_branch: Option<String>,
+    description: Option<String>,
+    star_count: Option<u64>,
+}
+
+/// Host and project path of a link into GitLab, which is everything
+/// ahead of the `/-/` separating it from the page within the project.
+fn project(url: &str) -> Option<(String, String)> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let host = url.host_str()?.to_lowercase();
+    let segments: Vec<&str> = url
+        .path_segments()?
+        .take_while(|segment| *segment != "-")
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    if segments.len() < 2 || RESERVED.contains(&segments[0]) {
+        return None;
+    }
+
+    let path = segments.join("/");
+    let path = path.strip_suffix(".git").unwrap_or(&path).to_string();
+    Some((host, path))
+}
+
+/// Whether a link is on gitlab.com, or on a page generated by GitLab.
+pub fn is_gitlab(url: &str, generator: Option<&str>) -> bool {
+    let on_gitlab = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            url.host_str()
+                .map(|host| host.eq_ignore_ascii_case("gitlab.com"))
+        })
+        .unwrap_or(false);
+
+    on_gitlab
+        || generator
+            .map(|generator| generator.to_lowercase().contains("gitlab"))
+            .unwrap_or(false)
+}
+
+/// Look a project up through the instance's API, which also
+/// confirms a self-hosted page really belongs to GitLab.
+pub async fn lookup(url: &str) -> Option<Special> {
+    let (instance, path) = project(url)?;
+    let api = format!(
+        "https://{}/api/v4/projects/{}",
+        instance,
+        utf8_percent_encode(&path, NON_ALPHANUMERIC)
+    );
+
+    let (resp, _) = fetch(&api).await.ok()?;
+    let project: Project = consume_json(resp).await.ok()?;
+
+    Some(Special::GitLab {
+        instance,
+        path: project.path_with_namespace,
+        default_branch: project.default_branch,
+        description: project.description.filter(|text| !text.is_empty()),
+        stars: project.star_count,
+    })
+}
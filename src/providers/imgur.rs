@@ -0,0 +1,78 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::structs::special::{ImgurType, Special};
+use crate::util::request::{consume_json, fetch_with_header};
+use crate::util::variables::IMGUR_CLIENT_ID;
+
+lazy_static! {
+    // Newer links put the title in front of the hash, `gallery/some-title-AbC12`.
+    static ref RE_ALBUM: Regex = Regex::new(
+        "^(?:https?://)?(?:www\\.|m\\.)?imgur\\.com/(a|gallery)/(?:[\\w-]+-)?([A-Za-z0-9]{5,})(?:$|[/?#])"
+    )
+    .unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_ALBUM];
+    patterns.len()
+}
+
+#[derive(Deserialize)]
+struct Album {
+    #[serde(default = "single")]
+    is_album: bool,
+    images_count: Option<usize>,
+}
+
+// Albums don't say they are one, gallery posts do.
+fn single() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct Response {
+    data: Album,
+}
+
+pub fn is_album(url: &str) -> bool {
+    RE_ALBUM.is_match(url)
+}
+
+/// Build a special for an album or gallery post. The image count
+/// needs the Imgur API, the pages themselves only list the first.
+pub async fn lookup(url: &str) -> Option<Special> {
+    let captures = RE_ALBUM.captures(url)?;
+    let content_type = match &captures[1] {
+        "a" => ImgurType::Album,
+        _ => ImgurType::Gallery,
+    };
+
+    let id = captures[2].to_string();
+    let count = count(&content_type, &id).await;
+
+    Some(Special::Imgur {
+        content_type,
+        id,
+        count,
+    })
+}
+
+async fn count(content_type: &ImgurType, id: &str) -> Option<usize> {
+    let client_id = IMGUR_CLIENT_ID.as_ref()?;
+    let api = match content_type {
+        ImgurType::Album => format!("https://api.imgur.com/3/album/{}", id),
+        ImgurType::Gallery => format!("https://api.imgur.com/3/gallery/{}", id),
+    };
+
+    let (resp, _) = fetch_with_header(&api, "Authorization", &format!("Client-ID {}", client_id))
+        .await
+        .ok()?;
+    let album = consume_json::<Response>(resp).await.ok()?.data;
+    if album.is_album {
+        album.images_count
+    } else {
+        Some(1)
+    }
+}
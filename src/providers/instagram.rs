@@ -0,0 +1,23 @@
+use serde::Deserialize;
+
+use crate::util::request::{consume_json, fetch};
+use crate::util::variables::FACEBOOK_TOKEN;
+
+#[derive(Deserialize)]
+pub struct OEmbed {
+    pub title: Option<String>,
+    pub author_name: Option<String>,
+    pub thumbnail_url: Option<String>,
+}
+
+/// Look up a post through the Instagram oEmbed endpoint, if we have a token for it.
+pub async fn oembed(url: &str) -> Option<OEmbed> {
+    let token = FACEBOOK_TOKEN.as_ref()?;
+    let mut api = reqwest::Url::parse("https://graph.facebook.com/v16.0/instagram_oembed").ok()?;
+    api.query_pairs_mut()
+        .append_pair("url", url)
+        .append_pair("access_token", token);
+
+    let (resp, _) = fetch(api.as_str()).await.ok()?;
+    consume_json(resp).await.ok()
+}
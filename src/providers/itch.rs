@@ -0,0 +1,29 @@
+use serde::Deserialize;
+
+use crate::structs::special::Special;
+use crate::util::request::{consume_json, fetch};
+
+#[derive(Deserialize)]
+struct Game {
+    id: Option<u64>,
+    title: Option<String>,
+    price: Option<String>,
+    cover_image: Option<String>,
+    #[serde(default)]
+    platforms: Vec<String>,
+}
+
+/// Look up a game through the data.json every itch.io game page exposes.
+pub async fn game(url: &str, cover: Option<String>) -> Option<Special> {
+    let url = format!("{}/data.json", url.split('?').next()?.trim_end_matches('/'));
+    let (resp, _) = fetch(&url).await.ok()?;
+    let game: Game = consume_json(resp).await.ok()?;
+
+    Some(Special::Itch {
+        id: game.id?.to_string(),
+        title: game.title,
+        price: game.price,
+        platforms: game.platforms,
+        cover: game.cover_image.or(cover),
+    })
+}
@@ -0,0 +1,67 @@
+use serde::Deserialize;
+
+use crate::util::request::{consume_json, fetch};
+use crate::util::result::Error;
+
+#[derive(Deserialize)]
+pub struct Icon {
+    src: String,
+    #[serde(default)]
+    sizes: String,
+    #[serde(default)]
+    purpose: String,
+}
+
+impl Icon {
+    /// Largest edge out of the listed sizes, `any` being scalable.
+    fn size(&self) -> u32 {
+        self.sizes
+            .split_whitespace()
+            .filter_map(|size| match size {
+                "any" => Some(u32::MAX),
+                size => size.split_once('x')?.0.parse().ok(),
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Manifest {
+    #[serde(skip)]
+    url: String,
+    pub name: Option<String>,
+    pub short_name: Option<String>,
+    pub theme_color: Option<String>,
+    pub background_color: Option<String>,
+    #[serde(default)]
+    icons: Vec<Icon>,
+}
+
+impl Manifest {
+    /// Absolute URL of the largest icon, avoiding maskable icons
+    /// as they are padded for the platform to crop.
+    pub fn icon_url(&self) -> Option<String> {
+        let icon = self
+            .icons
+            .iter()
+            .filter(|icon| {
+                icon.purpose.is_empty() || icon.purpose.split_whitespace().any(|p| p == "any")
+            })
+            .max_by_key(|icon| icon.size())?;
+
+        reqwest::Url::parse(&self.url)
+            .and_then(|base| base.join(&icon.src))
+            .ok()
+            .map(|url| url.to_string())
+    }
+}
+
+/// Fetch a web app manifest linked from a page.
+pub async fn lookup(url: &str) -> Result<Manifest, Error> {
+    let (resp, _) = fetch(url).await?;
+    let mut manifest: Manifest = consume_json(resp).await?;
+    manifest.url = url.to_string();
+
+    Ok(manifest)
+}
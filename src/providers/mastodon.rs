@@ -0,0 +1,113 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use super::activitypub::{self, text_of};
+use crate::structs::special::Special;
+use crate::util::request::{consume_json, fetch};
+
+lazy_static! {
+    static ref RE_STATUS: Regex = Regex::new(
+        "^https?://([^/?#@]+)/(?:@([\\w.-]+(?:@[\\w.-]+)?)|users/([\\w.-]+)/statuses)/(\\d+)(?:$|[/?#])"
+    )
+    .unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_STATUS];
+    patterns.len()
+}
+
+#[derive(Deserialize)]
+struct Account {
+    acct: String,
+    display_name: Option<String>,
+    avatar: Option<String>,
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Attachment {
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Status {
+    id: String,
+    account: Account,
+    content: Option<String>,
+    spoiler_text: Option<String>,
+    created_at: Option<String>,
+    #[serde(default)]
+    sensitive: bool,
+    #[serde(default)]
+    media_attachments: Vec<Attachment>,
+}
+
+/// Look up a status on whichever instance the link points at. Any site
+/// could use these paths, so it only counts once either the instance's
+/// Mastodon API or the page's ActivityPub alternate vouches for it.
+pub async fn lookup(url: &str, activity_url: Option<&str>) -> Option<Special> {
+    let captures = RE_STATUS.captures(url)?;
+    let instance = captures[1].to_lowercase();
+    let user = captures.get(2).or_else(|| captures.get(3))?.as_str();
+    let id = &captures[4];
+
+    if let Some(special) = from_api(&instance, id).await {
+        return Some(special);
+    }
+
+    match activitypub::lookup(activity_url?).await? {
+        Special::Fediverse {
+            author_url,
+            author_name,
+            author_avatar,
+            content,
+            content_warning,
+            published,
+            sensitive,
+            attachments,
+            ..
+        } => Some(Special::Mastodon {
+            id: id.to_string(),
+            instance,
+            author: user.to_string(),
+            author_name,
+            author_url,
+            author_avatar,
+            content,
+            content_warning,
+            published,
+            sensitive,
+            attachments,
+        }),
+        _ => None,
+    }
+}
+
+async fn from_api(instance: &str, id: &str) -> Option<Special> {
+    let api = format!("https://{}/api/v1/statuses/{}", instance, id);
+    let (resp, _) = fetch(&api).await.ok()?;
+    let status: Status = consume_json(resp).await.ok()?;
+    if status.id != id {
+        return None;
+    }
+
+    Some(Special::Mastodon {
+        id: status.id,
+        instance: instance.to_string(),
+        author: status.account.acct,
+        author_name: status.account.display_name.filter(|name| !name.is_empty()),
+        author_url: status.account.url,
+        author_avatar: status.account.avatar,
+        content: status.content.as_deref().map(text_of),
+        content_warning: status.spoiler_text.filter(|text| !text.is_empty()),
+        published: status.created_at,
+        sensitive: status.sensitive,
+        attachments: status
+            .media_attachments
+            .into_iter()
+            .filter_map(|attachment| attachment.url)
+            .collect(),
+    })
+}
@@ -0,0 +1,84 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::structs::special::Special;
+use crate::util::request::{consume_json, fetch, post_json};
+use crate::util::variables::MATRIX_HOMESERVER;
+
+#[derive(Deserialize)]
+struct Directory {
+    room_id: String,
+}
+
+#[derive(Deserialize)]
+struct PublicRooms {
+    chunk: Vec<PublicRoom>,
+}
+
+#[derive(Deserialize)]
+struct PublicRoom {
+    room_id: String,
+    name: Option<String>,
+    topic: Option<String>,
+    avatar_url: Option<String>,
+    num_joined_members: Option<usize>,
+}
+
+/// Convert a `mxc://server/media` URI to a thumbnail on our homeserver.
+fn thumbnail(mxc: &str) -> Option<String> {
+    let media = mxc.strip_prefix("mxc://")?;
+    Some(format!(
+        "{}/_matrix/media/v3/thumbnail/{}?width=128&height=128",
+        *MATRIX_HOMESERVER, media
+    ))
+}
+
+/// Resolve a room alias or ID into a special, using the room directory when public.
+pub async fn lookup(room: &str, event: Option<&str>) -> Option<Special> {
+    let (_, server) = room.split_once(':')?;
+    let room_id = if room.starts_with('#') {
+        let url = format!(
+            "{}/_matrix/client/v3/directory/room/{}",
+            *MATRIX_HOMESERVER,
+            room.replace('#', "%23")
+        );
+
+        let (resp, _) = fetch(&url).await.ok()?;
+        consume_json::<Directory>(resp).await.ok()?.room_id
+    } else {
+        room.to_string()
+    };
+
+    // Public room information is only available through the directory search.
+    let mut details = None;
+    let mut url = reqwest::Url::parse(&format!(
+        "{}/_matrix/client/v3/publicRooms",
+        *MATRIX_HOMESERVER
+    ))
+    .ok()?;
+    url.query_pairs_mut().append_pair("server", server);
+
+    if let Ok((resp, _)) = post_json(
+        url.as_str(),
+        &json!({ "filter": { "generic_search_term": room }, "limit": 20 }),
+    )
+    .await
+    {
+        if let Ok(rooms) = consume_json::<PublicRooms>(resp).await {
+            details = rooms.chunk.into_iter().find(|r| r.room_id == room_id);
+        }
+    }
+
+    Some(Special::Matrix {
+        alias: Some(room.to_string()).filter(|room| room.starts_with('#')),
+        event_id: event.map(str::to_string),
+        name: details.as_ref().and_then(|d| d.name.clone()),
+        topic: details.as_ref().and_then(|d| d.topic.clone()),
+        avatar: details
+            .as_ref()
+            .and_then(|d| d.avatar_url.as_deref())
+            .and_then(thumbnail),
+        members: details.as_ref().and_then(|d| d.num_joined_members),
+        room_id,
+    })
+}
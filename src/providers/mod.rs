@@ -0,0 +1,30 @@
+pub mod activitypub;
+pub mod archive;
+pub mod artwork;
+pub mod bluesky;
+pub mod campaign;
+pub mod data;
+pub mod discord;
+pub mod drive;
+pub mod facebook;
+pub mod frontends;
+pub mod github;
+pub mod gitlab;
+pub mod imgur;
+pub mod instagram;
+pub mod itch;
+pub mod manifest;
+pub mod mastodon;
+pub mod matrix;
+pub mod paste;
+pub mod photo;
+pub mod reddit;
+pub mod revolt;
+pub mod slides;
+pub mod songlink;
+pub mod steam;
+pub mod tiktok;
+pub mod twitch;
+pub mod twitter;
+pub mod wayback;
+pub mod wikipedia;
@@ -0,0 +1,134 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::structs::special::{PasteProvider, Special};
+use crate::util::request::{consume_bytes, consume_json, fetch};
+
+const MAX_PREVIEW_LINES: usize = 20;
+const MAX_PREVIEW_BYTES: usize = 2000;
+
+/// Cut content down to a preview, returning whether anything was dropped.
+fn preview(content: &str) -> (String, bool) {
+    let lines: Vec<&str> = content.trim_end().lines().collect();
+    let mut truncated = lines.len() > MAX_PREVIEW_LINES;
+    let mut preview = lines[..lines.len().min(MAX_PREVIEW_LINES)].join("\n");
+
+    if preview.len() > MAX_PREVIEW_BYTES {
+        let mut end = MAX_PREVIEW_BYTES;
+        while !preview.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        preview.truncate(end);
+        truncated = true;
+    }
+
+    (preview, truncated)
+}
+
+/// Preview the start of a paste, read up to one byte past the limit so
+/// that whether there was more is known from the read itself.
+fn preview_start(mut bytes: Vec<u8>) -> (String, bool) {
+    let cut = bytes.len() > MAX_PREVIEW_BYTES;
+    bytes.truncate(MAX_PREVIEW_BYTES);
+
+    // Drop a character split by the limit rather than decode half of it.
+    let start = bytes.len().saturating_sub(4);
+    if let Some(last) = (start..bytes.len())
+        .rev()
+        .find(|&at| bytes[at] & 0xC0 != 0x80)
+    {
+        if let Err(error) = std::str::from_utf8(&bytes[last..]) {
+            if error.error_len().is_none() {
+                bytes.truncate(last);
+            }
+        }
+    }
+
+    let (content, truncated) = preview(&String::from_utf8_lossy(&bytes));
+    (content, truncated || cut)
+}
+
+/// Fetch the raw paste and build a preview of it.
+pub async fn raw(provider: PasteProvider, url: &str, language: Option<String>) -> Option<Special> {
+    let (resp, _) = fetch(url).await.ok()?;
+    let bytes = consume_bytes(resp, MAX_PREVIEW_BYTES + 1).await.ok()?;
+    let (content, truncated) = preview_start(bytes);
+
+    Some(Special::Paste {
+        provider,
+        language,
+        content,
+        truncated,
+    })
+}
+
+#[derive(Deserialize)]
+struct Gist {
+    files: BTreeMap<String, GistFile>,
+}
+
+#[derive(Deserialize)]
+struct GistFile {
+    language: Option<String>,
+    content: Option<String>,
+}
+
+/// Look up a gist through the GitHub API, previewing its first file.
+pub async fn gist(id: &str) -> Option<Special> {
+    let url = format!("https://api.github.com/gists/{}", id);
+    let (resp, _) = fetch(&url).await.ok()?;
+    let gist: Gist = consume_json(resp).await.ok()?;
+    let (_, file) = gist.files.into_iter().next()?;
+    let (content, truncated) = preview(file.content.as_deref()?);
+
+    Some(Special::Paste {
+        provider: PasteProvider::Gist,
+        language: file.language,
+        content,
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previews_whole_pastes() {
+        assert_eq!(preview("a\nb\n\n"), ("a\nb".to_string(), false));
+        assert_eq!(preview("a\r\nb\r\n"), ("a\nb".to_string(), false));
+    }
+
+    #[test]
+    fn truncates_long_pastes() {
+        let (content, truncated) = preview(&"line\r\n".repeat(MAX_PREVIEW_LINES + 1));
+        assert_eq!(content.lines().count(), MAX_PREVIEW_LINES);
+        assert!(truncated);
+
+        let (content, truncated) = preview(&"\u{e9}".repeat(MAX_PREVIEW_BYTES));
+        assert!(content.len() <= MAX_PREVIEW_BYTES);
+        assert!(truncated);
+    }
+
+    #[test]
+    fn truncates_at_the_read_limit() {
+        // Shorter than the limit once line endings are normalised.
+        let paste = format!("{}\r\n", "a".repeat(198)).repeat(11);
+        let (content, truncated) =
+            preview_start(paste.as_bytes()[..MAX_PREVIEW_BYTES + 1].to_vec());
+        assert!(content.len() < MAX_PREVIEW_BYTES);
+        assert!(truncated);
+
+        // The limit falls in the middle of a character.
+        let paste = "\u{e9}".repeat(MAX_PREVIEW_BYTES);
+        let (content, truncated) =
+            preview_start(paste.as_bytes()[..MAX_PREVIEW_BYTES + 1].to_vec());
+        assert_eq!(content, "\u{e9}".repeat(MAX_PREVIEW_BYTES / 2));
+        assert!(truncated);
+
+        let (content, truncated) = preview_start(b"whole".to_vec());
+        assert_eq!(content, "whole");
+        assert!(!truncated);
+    }
+}
@@ -0,0 +1,84 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::structs::special::{PhotoProvider, Special};
+use crate::util::request::{consume_json, fetch};
+
+lazy_static! {
+    static ref RE_AUTHOR: Regex = Regex::new("^Photo by (.+?) on Unsplash").unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_AUTHOR];
+    patterns.len()
+}
+
+#[derive(Deserialize)]
+struct FlickrOEmbed {
+    title: Option<String>,
+    author_name: Option<String>,
+    author_url: Option<String>,
+    url: Option<String>,
+    width: Option<isize>,
+    height: Option<isize>,
+}
+
+/// Look up a photo through Flickr's oEmbed endpoint.
+pub async fn flickr(url: &str) -> Option<Special> {
+    let mut api = reqwest::Url::parse("https://www.flickr.com/services/oembed/").ok()?;
+    api.query_pairs_mut()
+        .append_pair("url", url)
+        .append_pair("format", "json");
+
+    let (resp, _) = fetch(api.as_str()).await.ok()?;
+    let oembed: FlickrOEmbed = consume_json(resp).await.ok()?;
+    let image = oembed.url?;
+
+    Some(Special::Photo {
+        provider: PhotoProvider::Flickr,
+        title: oembed.title,
+        author: oembed.author_name,
+        author_url: oembed.author_url,
+        // Flickr suffixes select the rendition, _n being 320px on the longest side.
+        thumbnail: Some(image.replacen("_b.", "_n.", 1)).filter(|thumb| thumb != &image),
+        image,
+        width: oembed.width,
+        height: oembed.height,
+    })
+}
+
+/// Request a rendition from Unsplash's image CDN at the given width.
+fn unsplash_rendition(url: &str, width: usize) -> Option<String> {
+    let mut url = reqwest::Url::parse(url).ok()?;
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !matches!(key.as_ref(), "w" | "h" | "fit" | "crop"))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("w", &width.to_string())
+        .append_pair("fit", "max");
+
+    Some(url.to_string())
+}
+
+/// Build an Unsplash photo from the page's own metadata.
+pub fn unsplash(title: Option<&str>, image: Option<&str>) -> Option<Special> {
+    let image = image?;
+    Some(Special::Photo {
+        provider: PhotoProvider::Unsplash,
+        author: title
+            .and_then(|title| RE_AUTHOR.captures(title))
+            .map(|captures| captures[1].to_string()),
+        title: title.map(str::to_string),
+        author_url: None,
+        thumbnail: unsplash_rendition(image, 400),
+        image: unsplash_rendition(image, 1920)?,
+        width: None,
+        height: None,
+    })
+}
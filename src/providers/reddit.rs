@@ -0,0 +1,131 @@
+use regex::Regex;
+use serde_json::Value;
+
+use crate::structs::special::{RedditType, Special};
+use crate::util::request::{consume_json, fetch};
+
+const MAX_TEXT_LENGTH: usize = 1000;
+
+lazy_static! {
+    static ref RE_POST: Regex = Regex::new(
+        "^(?:https?://)?(?:(?:www|old|new|np)\\.)?reddit\\.com/r/(\\w+)/comments/(\\w+)(?:/[^/?#]*(?:/(\\w+))?)?"
+    )
+    .unwrap();
+    static ref RE_SUBREDDIT: Regex =
+        Regex::new("^(?:https?://)?(?:(?:www|old|new|np)\\.)?reddit\\.com/r/(\\w+)/?(?:$|[?#])")
+            .unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_POST, &RE_SUBREDDIT];
+    patterns.len()
+}
+
+pub fn is_reddit(url: &str) -> bool {
+    RE_POST.is_match(url) || RE_SUBREDDIT.is_match(url)
+}
+
+fn string(value: &Value) -> Option<String> {
+    value
+        .as_str()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// Cut long self posts and comments down for the embed.
+fn text(value: &Value) -> Option<String> {
+    let mut text = string(value)?;
+    if text.len() > MAX_TEXT_LENGTH {
+        let mut end = MAX_TEXT_LENGTH;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        text.truncate(end);
+        text.push('…');
+    }
+
+    Some(text)
+}
+
+/// Preview images are given HTML escaped.
+fn image(post: &Value) -> Option<String> {
+    string(&post["preview"]["images"][0]["source"]["url"])
+        .map(|url| url.replace("&amp;", "&"))
+        .or_else(|| string(&post["thumbnail"]).filter(|url| url.starts_with("http")))
+}
+
+/// Look up a post, comment or subreddit through Reddit's JSON API,
+/// which has far more to go on than the page's OpenGraph tags.
+pub async fn lookup(url: &str) -> Option<Special> {
+    if let Some(captures) = RE_POST.captures(url) {
+        let subreddit = captures[1].to_string();
+        let id = captures[2].to_string();
+        let comment = captures.get(3).map(|m| m.as_str().to_string());
+
+        let api = match &comment {
+            Some(comment) => format!(
+                "https://www.reddit.com/r/{}/comments/{}/_/{}.json",
+                subreddit, id, comment
+            ),
+            None => format!(
+                "https://www.reddit.com/r/{}/comments/{}.json",
+                subreddit, id
+            ),
+        };
+
+        let (resp, _) = fetch(&api).await.ok()?;
+        let listings: Value = consume_json(resp).await.ok()?;
+        let post = &listings[0]["data"]["children"][0]["data"];
+        if !post.is_object() {
+            return None;
+        }
+
+        // Comment links return the comment first in the second listing.
+        let (content_type, item, description) = match &comment {
+            Some(_) => {
+                let comment = &listings[1]["data"]["children"][0]["data"];
+                (RedditType::Comment, comment, text(&comment["body"]))
+            }
+            None => (RedditType::Post, post, text(&post["selftext"])),
+        };
+
+        return Some(Special::Reddit {
+            content_type,
+            subreddit,
+            id: Some(id),
+            title: string(&post["title"]),
+            description,
+            author: string(&item["author"]),
+            score: item["score"].as_i64(),
+            subscribers: None,
+            image: image(post),
+        });
+    }
+
+    let captures = RE_SUBREDDIT.captures(url)?;
+    let subreddit = captures[1].to_string();
+    let api = format!("https://www.reddit.com/r/{}/about.json", subreddit);
+    let (resp, _) = fetch(&api).await.ok()?;
+    let about: Value = consume_json(resp).await.ok()?;
+    let about = &about["data"];
+    if !about.is_object() {
+        return None;
+    }
+
+    Some(Special::Reddit {
+        content_type: RedditType::Subreddit,
+        subreddit: string(&about["display_name"]).unwrap_or(subreddit),
+        id: None,
+        title: string(&about["title"]),
+        description: text(&about["public_description"]),
+        author: None,
+        score: None,
+        subscribers: about["subscribers"].as_i64(),
+        image: string(&about["community_icon"])
+            .or_else(|| string(&about["icon_img"]))
+            .map(|url| url.replace("&amp;", "&")),
+    })
+}
@@ -0,0 +1,107 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::structs::special::{RevoltType, Special};
+use crate::util::request::{consume_json, fetch, fetch_with_header};
+use crate::util::variables::{REVOLT_API_URL, REVOLT_APP_URL, REVOLT_AUTUMN_URL, REVOLT_BOT_TOKEN};
+
+lazy_static! {
+    static ref RE_REVOLT: Option<Regex> = REVOLT_APP_URL.as_ref().map(|app| {
+        Regex::new(&format!(
+            "^{}/(?:(invite)/([A-Za-z0-9]+)|(?:server/[A-Z0-9]{{26}}/)?(channel)/([A-Z0-9]{{26}})|(user)/([A-Z0-9]{{26}}))",
+            regex::escape(app.trim_end_matches('/'))
+        ))
+        .expect("Invalid JANUARY_REVOLT_APP_URL.")
+    });
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    // Only there when a Revolt instance is configured.
+    RE_REVOLT.iter().count()
+}
+
+#[derive(Deserialize)]
+struct File {
+    #[serde(rename = "_id")]
+    id: String,
+    tag: String,
+}
+
+#[derive(Deserialize)]
+struct Invite {
+    server_name: String,
+    server_icon: Option<File>,
+    channel_name: Option<String>,
+    channel_description: Option<String>,
+    member_count: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct Channel {
+    name: Option<String>,
+    description: Option<String>,
+    icon: Option<File>,
+}
+
+#[derive(Deserialize)]
+struct User {
+    username: String,
+    avatar: Option<File>,
+}
+
+fn file_url(file: &File) -> Option<String> {
+    REVOLT_AUTUMN_URL
+        .as_ref()
+        .map(|autumn| format!("{}/{}/{}", autumn.trim_end_matches('/'), file.tag, file.id))
+}
+
+async fn fetch_api<T: serde::de::DeserializeOwned>(path: &str) -> Option<T> {
+    let url = format!("{}{}", REVOLT_API_URL.as_ref()?.trim_end_matches('/'), path);
+    let (resp, _) = if let Some(token) = REVOLT_BOT_TOKEN.as_ref() {
+        fetch_with_header(&url, "x-bot-token", token).await.ok()?
+    } else {
+        fetch(&url).await.ok()?
+    };
+
+    consume_json(resp).await.ok()
+}
+
+/// Resolve links to the configured Revolt instance through its API.
+pub async fn lookup(url: &str) -> Option<Special> {
+    let captures = RE_REVOLT.as_ref()?.captures(url)?;
+
+    if let Some(code) = captures.get(2) {
+        let invite: Invite = fetch_api(&format!("/invites/{}", code.as_str())).await?;
+        Some(Special::Revolt {
+            content_type: RevoltType::Invite,
+            id: code.as_str().to_string(),
+            name: Some(invite.server_name),
+            description: invite.channel_description.or(invite.channel_name),
+            icon: invite.server_icon.as_ref().and_then(file_url),
+            member_count: invite.member_count,
+        })
+    } else if let Some(id) = captures.get(4) {
+        let channel: Channel = fetch_api(&format!("/channels/{}", id.as_str())).await?;
+        Some(Special::Revolt {
+            content_type: RevoltType::Channel,
+            id: id.as_str().to_string(),
+            name: channel.name,
+            description: channel.description,
+            icon: channel.icon.as_ref().and_then(file_url),
+            member_count: None,
+        })
+    } else if let Some(id) = captures.get(6) {
+        let user: User = fetch_api(&format!("/users/{}", id.as_str())).await?;
+        Some(Special::Revolt {
+            content_type: RevoltType::User,
+            id: id.as_str().to_string(),
+            name: Some(user.username),
+            description: None,
+            icon: user.avatar.as_ref().and_then(file_url),
+            member_count: None,
+        })
+    } else {
+        None
+    }
+}
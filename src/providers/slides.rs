@@ -0,0 +1,61 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::structs::special::{SlidesProvider, Special};
+use crate::util::request::{consume_json, fetch};
+
+lazy_static! {
+    static ref RE_PLAYER: Regex = Regex::new("/player/([0-9a-f]+)").unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_PLAYER];
+    patterns.len()
+}
+
+#[derive(Deserialize)]
+struct OEmbed {
+    title: Option<String>,
+    author_name: Option<String>,
+    html: Option<String>,
+    slideshow_id: Option<u64>,
+    total_slides: Option<usize>,
+}
+
+async fn oembed(endpoint: &str, url: &str) -> Option<OEmbed> {
+    let mut api = reqwest::Url::parse(endpoint).ok()?;
+    api.query_pairs_mut()
+        .append_pair("url", url)
+        .append_pair("format", "json");
+
+    let (resp, _) = fetch(api.as_str()).await.ok()?;
+    consume_json(resp).await.ok()
+}
+
+/// Look up a deck through SpeakerDeck's oEmbed endpoint.
+pub async fn speakerdeck(url: &str) -> Option<Special> {
+    let oembed = oembed("https://speakerdeck.com/oembed.json", url).await?;
+    let id = RE_PLAYER.captures(oembed.html.as_ref()?)?[1].to_string();
+
+    Some(Special::Slides {
+        provider: SlidesProvider::SpeakerDeck,
+        id,
+        title: oembed.title,
+        author: oembed.author_name,
+        pages: oembed.total_slides,
+    })
+}
+
+/// Look up a slideshow through SlideShare's oEmbed endpoint.
+pub async fn slideshare(url: &str) -> Option<Special> {
+    let oembed = oembed("https://www.slideshare.net/api/oembed/2", url).await?;
+
+    Some(Special::Slides {
+        provider: SlidesProvider::SlideShare,
+        id: oembed.slideshow_id?.to_string(),
+        title: oembed.title,
+        author: oembed.author_name,
+        pages: oembed.total_slides,
+    })
+}
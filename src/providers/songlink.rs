@@ -0,0 +1,32 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::util::request::{consume_json, fetch};
+
+#[derive(Deserialize)]
+struct Platform {
+    url: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Links {
+    links_by_platform: BTreeMap<String, Platform>,
+}
+
+/// Resolve a music URL through the Odesli API into a map of platform to URL.
+pub async fn lookup(url: &str) -> Option<BTreeMap<String, String>> {
+    let mut api = reqwest::Url::parse("https://api.song.link/v1-alpha.1/links").ok()?;
+    api.query_pairs_mut().append_pair("url", url);
+
+    let (resp, _) = fetch(api.as_str()).await.ok()?;
+    let links: Links = consume_json(resp).await.ok()?;
+
+    Some(
+        links
+            .links_by_platform
+            .into_iter()
+            .map(|(platform, link)| (platform, link.url))
+            .collect(),
+    )
+}
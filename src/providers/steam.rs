@@ -0,0 +1,96 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::structs::special::Special;
+use crate::util::request::{consume_json, fetch};
+
+lazy_static! {
+    static ref RE_APP: Regex =
+        Regex::new("^(?:https?://)?store\\.steampowered\\.com/app/(\\d+)").unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_APP];
+    patterns.len()
+}
+
+#[derive(Deserialize)]
+struct ReleaseDate {
+    #[serde(default)]
+    coming_soon: bool,
+    date: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Price {
+    final_formatted: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct App {
+    #[serde(default)]
+    is_free: bool,
+    header_image: Option<String>,
+    release_date: Option<ReleaseDate>,
+    price_overview: Option<Price>,
+}
+
+#[derive(Deserialize)]
+struct Details {
+    success: bool,
+    data: Option<App>,
+}
+
+pub fn is_app(url: &str) -> bool {
+    RE_APP.is_match(url)
+}
+
+/// Build a special for a store page, adding what the appdetails
+/// API knows about the app when it answers.
+pub async fn lookup(url: &str) -> Option<Special> {
+    let app_id = RE_APP.captures(url)?[1].to_string();
+    let app = details(&app_id).await;
+
+    let (price, release_date, coming_soon, header_image) = match app {
+        Some(app) => {
+            let price = match app.price_overview {
+                Some(price) => price.final_formatted,
+                None if app.is_free => Some("Free".to_string()),
+                None => None,
+            };
+
+            let (release_date, coming_soon) = match app.release_date {
+                Some(release) => (
+                    release.date.filter(|date| !date.is_empty()),
+                    release.coming_soon,
+                ),
+                None => (None, false),
+            };
+
+            (price, release_date, coming_soon, app.header_image)
+        }
+        None => (None, None, false, None),
+    };
+
+    Some(Special::Steam {
+        app_id,
+        price,
+        release_date,
+        coming_soon,
+        header_image,
+    })
+}
+
+async fn details(app_id: &str) -> Option<App> {
+    let mut api = reqwest::Url::parse("https://store.steampowered.com/api/appdetails").ok()?;
+    api.query_pairs_mut().append_pair("appids", app_id);
+
+    let (resp, _) = fetch(api.as_str()).await.ok()?;
+    let mut details: HashMap<String, Details> = consume_json(resp).await.ok()?;
+    details
+        .remove(app_id)
+        .filter(|details| details.success)
+        .and_then(|details| details.data)
+}
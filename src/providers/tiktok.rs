@@ -0,0 +1,19 @@
+use serde::Deserialize;
+
+use crate::util::request::{consume_json, fetch};
+
+#[derive(Deserialize)]
+pub struct OEmbed {
+    pub title: Option<String>,
+    pub author_name: Option<String>,
+    pub thumbnail_url: Option<String>,
+}
+
+/// Look up a video through the TikTok oEmbed endpoint.
+pub async fn oembed(url: &str) -> Option<OEmbed> {
+    let mut api = reqwest::Url::parse("https://www.tiktok.com/oembed").ok()?;
+    api.query_pairs_mut().append_pair("url", url);
+
+    let (resp, _) = fetch(api.as_str()).await.ok()?;
+    consume_json(resp).await.ok()
+}
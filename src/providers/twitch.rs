@@ -0,0 +1,124 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::structs::media::Video;
+use crate::structs::special::Special;
+use crate::util::request::{consume_json, post_json_with_header};
+
+// Client ID used by Twitch's own web player, which can
+// request clip playback tokens without any credentials.
+const CLIENT_ID: &str = "kimne78kx3ncx6brgo4mv6wki5h1ko";
+const CLIP_QUERY_HASH: &str = "36b89d2507fce29e5ca551df756d27c1cfe079e2609642b4390aa4c35796eb11";
+
+#[derive(Deserialize)]
+struct Response {
+    data: Data,
+}
+
+#[derive(Deserialize)]
+struct Data {
+    clip: Option<Clip>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Clip {
+    playback_access_token: AccessToken,
+    video_qualities: Vec<Quality>,
+}
+
+#[derive(Deserialize)]
+struct AccessToken {
+    signature: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Quality {
+    quality: String,
+    #[serde(rename = "sourceURL")]
+    source_url: String,
+}
+
+/// Find the best direct MP4 rendition of a clip.
+pub async fn clip_video(slug: &str) -> Option<Video> {
+    let body = json!({
+        "operationName": "VideoAccessToken_Clip",
+        "variables": { "slug": slug },
+        "extensions": {
+            "persistedQuery": { "version": 1, "sha256Hash": CLIP_QUERY_HASH }
+        }
+    });
+
+    let (resp, _) =
+        post_json_with_header("https://gql.twitch.tv/gql", &body, "Client-Id", CLIENT_ID)
+            .await
+            .ok()?;
+    let clip = consume_json::<Response>(resp).await.ok()?.data.clip?;
+
+    let (height, quality) = clip
+        .video_qualities
+        .iter()
+        .filter_map(|q| Some((q.quality.parse::<isize>().ok()?, q)))
+        .max_by_key(|(height, _)| *height)?;
+
+    let mut url = reqwest::Url::parse(&quality.source_url).ok()?;
+    url.query_pairs_mut()
+        .append_pair("sig", &clip.playback_access_token.signature)
+        .append_pair("token", &clip.playback_access_token.value);
+
+    Some(Video {
+        url: url.to_string(),
+        width: height * 16 / 9,
+        height,
+        framerate: None,
+        audio: None,
+    })
+}
+
+#[derive(Deserialize)]
+struct CategoryResponse {
+    data: CategoryData,
+}
+
+#[derive(Deserialize)]
+struct CategoryData {
+    game: Option<Game>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Game {
+    display_name: String,
+    #[serde(rename = "boxArtURL")]
+    box_art_url: Option<String>,
+    broadcasters_count: Option<usize>,
+    viewers_count: Option<usize>,
+}
+
+/// Look up a directory category by its slug.
+pub async fn category(slug: &str) -> Option<Special> {
+    let body = json!({
+        "query": "query($slug: String) { game(slug: $slug) { displayName boxArtURL(width: 285, height: 380) broadcastersCount viewersCount } }",
+        "variables": { "slug": slug }
+    });
+
+    let (resp, _) =
+        post_json_with_header("https://gql.twitch.tv/gql", &body, "Client-Id", CLIENT_ID)
+            .await
+            .ok()?;
+    let game = consume_json::<CategoryResponse>(resp)
+        .await
+        .ok()?
+        .data
+        .game?;
+
+    Some(Special::TwitchCategory {
+        id: slug.to_string(),
+        name: game.display_name,
+        box_art: game.box_art_url,
+        channels: game.broadcasters_count,
+        viewers: game.viewers_count,
+    })
+}
@@ -0,0 +1,73 @@
+use regex::Regex;
+use scraper::{Html, Selector};
+use serde::Deserialize;
+
+use crate::structs::special::Special;
+use crate::util::request::{consume_json, fetch};
+
+lazy_static! {
+    static ref RE_STATUS: Regex = Regex::new(
+        "^(?:https?://)?(?:www\\.|mobile\\.)?(?:twitter|x)\\.com/(\\w{1,15})/status(?:es)?/(\\d+)"
+    )
+    .unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_STATUS];
+    patterns.len()
+}
+
+#[derive(Deserialize)]
+struct OEmbed {
+    author_name: Option<String>,
+    html: Option<String>,
+}
+
+pub fn is_status(url: &str) -> bool {
+    RE_STATUS.is_match(url)
+}
+
+/// Build a special for a tweet, taking its text from publish.twitter.com
+/// since status pages no longer reliably carry OpenGraph tags.
+pub async fn lookup(url: &str, description: Option<String>) -> Option<Special> {
+    let captures = RE_STATUS.captures(url)?;
+    let (user, id) = (captures[1].to_string(), captures[2].to_string());
+
+    let oembed = oembed(&user, &id).await;
+    let author = oembed
+        .as_ref()
+        .and_then(|oembed| oembed.author_name.clone());
+
+    // The embed is a <blockquote> with the tweet in its first paragraph.
+    let text = oembed
+        .and_then(|oembed| oembed.html)
+        .and_then(|html| {
+            let selector = Selector::parse("p").ok()?;
+            Html::parse_fragment(&html)
+                .select(&selector)
+                .next()
+                .map(|p| p.text().collect::<String>().trim().to_string())
+        })
+        .or(description);
+
+    Some(Special::Twitter {
+        user,
+        id,
+        author,
+        text,
+    })
+}
+
+async fn oembed(user: &str, id: &str) -> Option<OEmbed> {
+    let mut api = reqwest::Url::parse("https://publish.twitter.com/oembed").ok()?;
+    api.query_pairs_mut()
+        .append_pair(
+            "url",
+            &format!("https://twitter.com/{}/status/{}", user, id),
+        )
+        .append_pair("omit_script", "true");
+
+    let (resp, _) = fetch(api.as_str()).await.ok()?;
+    consume_json(resp).await.ok()
+}
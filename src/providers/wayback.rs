@@ -0,0 +1,48 @@
+use january_types::Snapshot;
+use serde::Deserialize;
+
+use crate::util::request::{consume_json, fetch};
+
+#[derive(Deserialize)]
+struct Availability {
+    archived_snapshots: Snapshots,
+}
+
+#[derive(Deserialize)]
+struct Snapshots {
+    closest: Option<Closest>,
+}
+
+#[derive(Deserialize)]
+struct Closest {
+    available: bool,
+    timestamp: String,
+}
+
+/// Find the latest Wayback Machine snapshot of a URL.
+pub async fn lookup(url: &str) -> Option<Snapshot> {
+    let mut api = reqwest::Url::parse("https://archive.org/wayback/available").ok()?;
+    api.query_pairs_mut().append_pair("url", url);
+
+    let (resp, _) = fetch(api.as_str()).await.ok()?;
+    let closest = consume_json::<Availability>(resp)
+        .await
+        .ok()?
+        .archived_snapshots
+        .closest
+        .filter(|closest| closest.available)?;
+
+    Some(Snapshot {
+        url: format!("https://web.archive.org/web/{}/{}", closest.timestamp, url),
+        timestamp: closest.timestamp,
+    })
+}
+
+/// The archived document itself, as it was served.
+pub fn raw_url(url: &str, snapshot: &Snapshot) -> String {
+    // The id_ flag serves the original document without the Wayback toolbar.
+    format!(
+        "https://web.archive.org/web/{}id_/{}",
+        snapshot.timestamp, url
+    )
+}
@@ -0,0 +1,88 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::structs::special::Special;
+use crate::util::request::{consume_json, fetch};
+
+lazy_static! {
+    static ref RE_ARTICLE: Regex =
+        Regex::new("^(?:https?://)?([a-z][a-z0-9-]*)(?:\\.m)?\\.wikipedia\\.org/wiki/([^?#]+)")
+            .unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_ARTICLE];
+    patterns.len()
+}
+
+#[derive(Deserialize)]
+struct Thumbnail {
+    source: String,
+}
+
+#[derive(Deserialize)]
+struct Summary {
+    title: String,
+    extract: Option<String>,
+    thumbnail: Option<Thumbnail>,
+}
+
+pub fn is_article(url: &str) -> bool {
+    RE_ARTICLE.is_match(url)
+}
+
+/// Build a special from the REST summary of an article, as the
+/// OpenGraph description Wikipedia serves is usually empty.
+pub async fn lookup(url: &str) -> Option<Special> {
+    let captures = RE_ARTICLE.captures(url)?;
+    let language = captures[1].to_string();
+
+    let api = format!(
+        "https://{}.wikipedia.org/api/rest_v1/page/summary/{}",
+        language, &captures[2]
+    );
+
+    let (resp, _) = fetch(&api).await.ok()?;
+    let summary: Summary = consume_json(resp).await.ok()?;
+
+    Some(Special::Wikipedia {
+        language,
+        title: summary.title,
+        extract: summary.extract.filter(|extract| !extract.is_empty()),
+        thumbnail: summary.thumbnail.map(|thumbnail| thumbnail.source),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::cassette::{self, Cassette};
+    use actix_web::rt::System;
+    use std::path::Path;
+
+    #[test]
+    fn looks_up_summaries() {
+        let cassette = Cassette {
+            dir: Path::new(env!("CARGO_MANIFEST_DIR")).join("cassettes"),
+            record: false,
+        };
+        let url = "https://en.wikipedia.org/wiki/Rust_(programming_language)";
+        let special = System::new().block_on(cassette::scope(cassette, lookup(url)));
+
+        match special {
+            Some(Special::Wikipedia {
+                language,
+                title,
+                extract,
+                thumbnail,
+            }) => {
+                assert_eq!(language, "en");
+                assert_eq!(title, "Rust (programming language)");
+                assert!(extract.unwrap().starts_with("Rust is"));
+                assert!(thumbnail.unwrap().ends_with(".png"));
+            }
+            _ => panic!("expected a Wikipedia special"),
+        }
+    }
+}
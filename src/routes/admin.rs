@@ -0,0 +1,43 @@
+use actix_web::{web::Json, HttpRequest, Responder};
+
+use crate::util::bandwidth;
+use crate::util::metrics;
+use crate::util::quota::same_secret;
+use crate::util::result::Error;
+use crate::util::variables::{ADMIN_TOKEN, PREWARM_TOKEN};
+
+fn presents(req: &HttpRequest, token: Option<&String>) -> bool {
+    match (token, req.headers().get("Authorization")) {
+        (Some(token), Some(provided)) => same_secret(provided.as_bytes(), token.as_bytes()),
+        _ => false,
+    }
+}
+
+/// Check the request carries the operator token.
+pub fn authorize(req: &HttpRequest) -> Result<(), Error> {
+    if presents(req, ADMIN_TOKEN.as_ref()) {
+        Ok(())
+    } else {
+        Err(Error::Unauthorized)
+    }
+}
+
+/// Check the request may prewarm the cache, which the operator token
+/// allows as well.
+pub fn authorize_prewarm(req: &HttpRequest) -> Result<(), Error> {
+    if presents(req, PREWARM_TOKEN.as_ref()) {
+        Ok(())
+    } else {
+        authorize(req)
+    }
+}
+
+pub async fn bandwidth(req: HttpRequest) -> Result<impl Responder, Error> {
+    authorize(&req)?;
+    Ok(Json(bandwidth::snapshot()))
+}
+
+pub async fn metrics(req: HttpRequest) -> Result<impl Responder, Error> {
+    authorize(&req)?;
+    Ok(Json(metrics::snapshot()))
+}
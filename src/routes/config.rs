@@ -0,0 +1,84 @@
+use actix_web::web;
+use actix_web::Responder;
+use serde::Serialize;
+
+use crate::util::variables::{
+    DAILY_BYTE_QUOTA, DAILY_QUOTA, FFMPEG, FFPROBE, GIF_TRANSCODE_SIZE, MAX_DATA_URI_SIZE,
+    MAX_DOCUMENT_SIZE, MAX_EMBEDS_PER_REQUEST, MAX_URL_LENGTH, STATIC_MAP_URL, WAYBACK_FALLBACK,
+};
+
+#[derive(Debug, Serialize)]
+pub struct Limits {
+    max_url_length: usize,
+    max_document_size: usize,
+    max_data_uri_size: usize,
+    max_embeds_per_request: usize,
+    gif_transcode_size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    daily_quota: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    daily_byte_quota: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Formats {
+    // Images `/proxy` can scale down with `width`.
+    resize: Vec<&'static str>,
+    // Images `/proxy` re-encodes for clients that can't display them.
+    convert: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Features {
+    thumbnails: bool,
+    gif_transcoding: bool,
+    video_embeds: bool,
+    static_maps: bool,
+    wayback_fallback: bool,
+}
+
+/// What this instance allows and supports, so clients can adapt to
+/// however it was deployed. Nothing here should identify upstream
+/// hosts or credentials.
+#[derive(Debug, Serialize)]
+pub struct Config {
+    january: &'static str,
+    limits: Limits,
+    formats: Formats,
+    features: Features,
+}
+
+pub async fn get() -> impl Responder {
+    let mut convert = Vec::new();
+    if cfg!(feature = "heic") {
+        convert.extend(["heic", "heif"]);
+    }
+
+    if cfg!(feature = "jxl") {
+        convert.push("jxl");
+    }
+
+    web::Json(Config {
+        january: env!("CARGO_PKG_VERSION"),
+        limits: Limits {
+            max_url_length: *MAX_URL_LENGTH,
+            max_document_size: *MAX_DOCUMENT_SIZE,
+            max_data_uri_size: *MAX_DATA_URI_SIZE,
+            max_embeds_per_request: *MAX_EMBEDS_PER_REQUEST,
+            gif_transcode_size: *GIF_TRANSCODE_SIZE,
+            daily_quota: *DAILY_QUOTA,
+            daily_byte_quota: *DAILY_BYTE_QUOTA,
+        },
+        formats: Formats {
+            resize: vec!["jpeg", "png", "webp"],
+            convert,
+        },
+        features: Features {
+            thumbnails: FFMPEG.is_some(),
+            gif_transcoding: FFMPEG.is_some(),
+            video_embeds: FFPROBE.is_some(),
+            static_maps: STATIC_MAP_URL.is_some(),
+            wayback_fallback: *WAYBACK_FALLBACK,
+        },
+    })
+}
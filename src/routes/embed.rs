@@ -1,47 +1,220 @@
 use actix_web::{
     web::{self, Query},
-    Responder,
+    Either, HttpRequest, Responder,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
+use super::admin::authorize;
+use crate::providers::{facebook, frontends, revolt, twitter, wayback};
+use crate::structs::calendar::Event;
 use crate::structs::embed::Embed;
 use crate::structs::metadata::Metadata;
-use crate::util::request::fetch;
+use crate::structs::route::Route;
+use crate::util::cache::Cache;
+use crate::util::load;
+use crate::util::opt_out::opted_out;
+use crate::util::reputation;
+use crate::util::request::{fetch, fetch_page, validate_url};
+use crate::util::trace::{self, Upstream};
+use crate::util::variables::{
+    CACHE_TTL, DANGEROUS_CACHE_TTL, EMBED_CACHE_SIZE, MAX_DOCUMENT_SIZE, REFUSE_DANGEROUS,
+    WARNING_CACHE_TTL, WAYBACK_FALLBACK,
+};
 use crate::{
-    structs::media::{Image, ImageSize},
-    util::{request::consume_size, result::Error},
+    structs::media::{Image, ImageSize, Video},
+    util::{
+        ffmpeg,
+        request::{consume_bytes, consume_size, consume_text},
+        result::Error,
+    },
 };
 
+lazy_static! {
+    static ref CACHE: Cache<String, Embed> =
+        Cache::new(*EMBED_CACHE_SIZE, Duration::from_secs(*CACHE_TTL));
+}
+
+// How much of a video is read for ffprobe, enough for the streams'
+// headers when they come first.
+const PROBE_SIZE: usize = 4_000_000;
+
 #[derive(Deserialize)]
 pub struct Parameters {
     url: String,
+    // Include the upstream fetches made, for operators.
+    #[serde(default)]
+    debug: bool,
 }
 
-pub async fn get(info: Query<Parameters>) -> Result<impl Responder, Error> {
-    let url = info.into_inner().url;
-    let (resp, mime) = fetch(&url).await?;
+async fn generate(url: String) -> Result<Embed, Error> {
+    if let Some(warning) = reputation::check(&url).await {
+        if *REFUSE_DANGEROUS {
+            return Err(Error::DangerousUrl { warning });
+        }
+
+        return Ok(Embed::Website(Metadata::dangerous(url, warning)));
+    }
+
+    // Links to our own instance are resolved through its API
+    // rather than by scraping the frontend.
+    if let Some(special) = revolt::lookup(&url).await {
+        return Ok(Embed::Website(Metadata::from_special(url, special)));
+    }
+
+    let url = frontends::normalise(&url).unwrap_or(url);
+    if opted_out(&url).await {
+        return Ok(Embed::Website(Metadata::from_url(url)));
+    }
+
+    let user_agent = Some(facebook::USER_AGENT).filter(|_| facebook::is_facebook(&url));
+    let result = fetch_page(&url, user_agent).await;
+    if let Err(Error::DangerousUrl { warning }) = result {
+        if !*REFUSE_DANGEROUS {
+            return Ok(Embed::Website(Metadata::dangerous(url, warning)));
+        }
+    }
+
+    // Videos and posts are embedded by id, which the link already has.
+    if result.is_err() {
+        if let Some(special) = facebook::special(&url) {
+            return Ok(Embed::Website(Metadata::from_special(url, special)));
+        }
+    }
+
+    // Status pages are often refused to bots, but oEmbed still answers.
+    if result.is_err() && twitter::is_status(&url) {
+        if let Some(special) = twitter::lookup(&url, None).await {
+            return Ok(Embed::Website(Metadata::from_special(url, special)));
+        }
+    }
+
+    // Dead links can still be embedded from their last archived copy.
+    let mut snapshot = None;
+    let (resp, mime) = match result {
+        Err(Error::NotFound) | Err(Error::RequestTimedOut) if *WAYBACK_FALLBACK => {
+            let archived = wayback::lookup(&url).await.ok_or(Error::NotFound)?;
+            let result = fetch(&wayback::raw_url(&url, &archived)).await?;
+            snapshot = Some(archived);
+            result
+        }
+        result => result?,
+    };
 
     if let mime::HTML = mime.subtype() {
         let mut metadata = Metadata::from(resp, url).await?;
+        if metadata.is_opted_out() {
+            return Ok(Embed::Website(metadata.url_only()));
+        }
+
         metadata.resolve_external().await;
 
+        if let Some(snapshot) = snapshot {
+            metadata.set_archived(snapshot);
+        }
+
         if metadata.is_none() {
-            return Ok(web::Json(Embed::None));
+            return Ok(Embed::None);
         }
 
-        Ok(web::Json(Embed::Website(metadata)))
+        Ok(Embed::Website(metadata))
     } else if let mime::IMAGE = mime.type_() {
-        if let Ok((width, height)) = consume_size(resp).await {
-            Ok(web::Json(Embed::Image(Image {
+        if let Ok(probe) = consume_size(resp).await {
+            Ok(Embed::Image(Image {
                 url,
-                width,
-                height,
+                width: probe.width,
+                height: probe.height,
                 size: ImageSize::Large,
-            })))
+                animated: probe.animated,
+                size_bytes: probe.size_bytes,
+                format: probe.format.map(str::to_string),
+            }))
         } else {
-            Ok(web::Json(Embed::None))
+            Ok(Embed::None)
         }
+    } else if let mime::VIDEO = mime.type_() {
+        // Needs ffprobe, there's no cheap way to read a video's header ourselves.
+        let head = consume_bytes(resp, PROBE_SIZE).await?;
+        Ok(ffmpeg::probe(head)
+            .await
+            .map(|streams| {
+                Embed::Video(Video {
+                    url,
+                    width: streams.width,
+                    height: streams.height,
+                    framerate: streams.framerate,
+                    audio: Some(streams.audio),
+                })
+            })
+            .unwrap_or(Embed::None))
+    } else if mime.subtype() == "geo" || url.ends_with(".geojson") {
+        // GeoJSON is served as application/geo+json or, more often, plain JSON.
+        let document = consume_text(resp, *MAX_DOCUMENT_SIZE).await?;
+        Ok(Route::from_geojson(&document)
+            .map(Embed::Route)
+            .unwrap_or(Embed::None))
+    } else if mime.subtype() == "gpx" || url.ends_with(".gpx") {
+        let document = consume_text(resp, *MAX_DOCUMENT_SIZE).await?;
+        Ok(Route::from_gpx(&document)
+            .map(Embed::Route)
+            .unwrap_or(Embed::None))
+    } else if mime.type_() == mime::TEXT && mime.subtype() == "calendar" {
+        let calendar = consume_text(resp, *MAX_DOCUMENT_SIZE).await?;
+        Ok(Event::from(&calendar)
+            .map(Embed::Event)
+            .unwrap_or(Embed::None))
+    } else {
+        Ok(Embed::None)
+    }
+}
+
+pub async fn embed(url: String) -> Result<Embed, Error> {
+    let url = validate_url(&url)?;
+    if let Some(embed) = CACHE.get(&url) {
+        return Ok(embed);
+    }
+
+    refresh(url).await
+}
+
+/// Generate an embed anew, replacing whatever was cached for it.
+async fn refresh(url: String) -> Result<Embed, Error> {
+    let _permit = load::admit().await?;
+    let embed = generate(url.clone()).await?;
+    // Warnings are often an upstream having a bad moment, so try again soon.
+    // Blocklists change too, and a link may be cleared or newly flagged.
+    if embed.is_dangerous() {
+        CACHE.insert_for(
+            url,
+            embed.clone(),
+            Duration::from_secs(*DANGEROUS_CACHE_TTL),
+        );
+    } else if embed.has_warnings() {
+        CACHE.insert_for(url, embed.clone(), Duration::from_secs(*WARNING_CACHE_TTL));
     } else {
-        Ok(web::Json(Embed::None))
+        CACHE.insert(url, embed.clone());
+    }
+    Ok(embed)
+}
+
+#[derive(Serialize)]
+struct DebugResponse {
+    embed: Embed,
+    upstream: Vec<Upstream>,
+}
+
+pub async fn get(req: HttpRequest, info: Query<Parameters>) -> Result<impl Responder, Error> {
+    let Parameters { url, debug } = info.into_inner();
+    if !debug {
+        return Ok(Either::Left(web::Json(embed(url).await?)));
     }
+
+    // Upstream URLs can carry API keys, so only operators may see them.
+    // A cached embed made no fetches, so there would be nothing to show.
+    authorize(&req)?;
+    let embed = refresh(validate_url(&url)?).await?;
+    Ok(Either::Right(web::Json(DebugResponse {
+        embed,
+        upstream: trace::upstream(),
+    })))
 }
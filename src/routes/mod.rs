@@ -1,3 +1,8 @@
+pub mod admin;
+pub mod config;
 pub mod embed;
+pub mod info;
+pub mod prewarm;
 pub mod proxy;
-pub mod info;
\ No newline at end of file
+pub mod scan;
+pub mod thumbnail;
@@ -0,0 +1,39 @@
+use actix_web::{rt, web::Json, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::util::result::Error;
+use crate::util::variables::MAX_EMBEDS_PER_REQUEST;
+
+use super::admin::authorize_prewarm;
+use super::embed::embed;
+
+#[derive(Deserialize)]
+pub struct Body {
+    urls: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct Accepted {
+    accepted: usize,
+    truncated: usize,
+}
+
+pub async fn post(req: HttpRequest, body: Json<Body>) -> Result<impl Responder, Error> {
+    authorize_prewarm(&req)?;
+
+    let mut urls = body.into_inner().urls;
+    let truncated = urls.len().saturating_sub(*MAX_EMBEDS_PER_REQUEST);
+    urls.truncate(*MAX_EMBEDS_PER_REQUEST);
+
+    let accepted = urls.len();
+    for url in urls {
+        rt::spawn(async move {
+            embed(url).await.ok();
+        });
+    }
+
+    Ok(HttpResponse::Accepted().json(Accepted {
+        accepted,
+        truncated,
+    }))
+}
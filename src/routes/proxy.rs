@@ -1,25 +1,357 @@
-use actix_web::{web::Query, HttpResponse, Responder};
+use actix_web::dev::BodyEncoding;
+use actix_web::http::header::{
+    ContentEncoding, ACCEPT, CONTENT_DISPOSITION, CONTENT_SECURITY_POLICY, ETAG, LAST_MODIFIED,
+    VARY, X_CONTENT_TYPE_OPTIONS,
+};
+use actix_web::web::{self, Bytes, Query};
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder, Responder};
+use image::{imageops::FilterType, GenericImageView, ImageOutputFormat};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use serde::Deserialize;
+use std::time::Duration;
 
-use crate::util::request::fetch;
+use crate::util::cache::Cache;
+use crate::util::opt_out::opted_out;
+use crate::util::referer::refetch_image;
+use crate::util::request::{consume_bytes, is_animated, validate_url, MAX_PIXELS};
 use crate::util::result::Error;
+use crate::util::variables::{
+    CACHE_TTL, FFMPEG, GIF_TRANSCODE_SIZE, MAX_IMAGE_SIZE, PROXY_CACHE_BYTES, PROXY_CACHE_SIZE,
+};
+use crate::util::{bandwidth, ffmpeg, icc, load};
+
+// Characters allowed unescaped in an RFC 5987 extended value.
+const FILENAME: &AsciiSet = &NON_ALPHANUMERIC.remove(b'.').remove(b'-').remove(b'_');
+
+/// An image as fetched, along with what is needed to ask upstream
+/// whether it has changed since.
+#[derive(Clone)]
+struct Original {
+    body: Bytes,
+    content_type: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Original {
+    fn validators(&self) -> Vec<(&str, &str)> {
+        let mut validators = Vec::new();
+        if let Some(etag) = &self.etag {
+            validators.push(("If-None-Match", etag.as_str()));
+        }
+
+        if let Some(last_modified) = &self.last_modified {
+            validators.push(("If-Modified-Since", last_modified.as_str()));
+        }
+
+        validators
+    }
+}
+
+lazy_static! {
+    // Stale originals are kept until evicted, to be revalidated.
+    static ref CACHE: Cache<String, Original> = Cache::with_budget(
+        *PROXY_CACHE_SIZE,
+        *PROXY_CACHE_BYTES,
+        Duration::from_secs(*CACHE_TTL),
+        |original| original.body.len()
+    );
+    static ref RENDITIONS: Cache<(String, u32, u32), (Bytes, String, f32)> = Cache::with_budget(
+        *PROXY_CACHE_SIZE,
+        *PROXY_CACHE_BYTES,
+        Duration::from_secs(*CACHE_TTL),
+        |(body, ..)| body.len()
+    );
+}
+
+// JPEG XL originals decoded for clients which can't display them.
+#[cfg(feature = "jxl")]
+lazy_static! {
+    static ref DECODED: Cache<String, (Bytes, String)> = Cache::with_budget(
+        *PROXY_CACHE_SIZE,
+        *PROXY_CACHE_BYTES,
+        Duration::from_secs(*CACHE_TTL),
+        |(body, _)| body.len()
+    );
+}
 
 #[derive(Deserialize)]
 pub struct Parameters {
     url: String,
+    // Width the client will display the image at, in CSS pixels.
+    width: Option<u32>,
+    dpr: Option<f32>,
+    // `mp4` asks for large GIFs to be served as video.
+    format: Option<String>,
+}
+
+/// Scale an image down to `pixels` wide, returning the density
+/// it ends up at relative to `width`. Images already small enough
+/// and animations are passed through untouched, as only the first
+/// frame would survive re-encoding. This blocks, so is run with `web::block`.
+fn render(
+    (body, content_type): (Bytes, String),
+    width: u32,
+    pixels: u32,
+) -> Result<(Bytes, String, f32), Error> {
+    let size = imagesize::blob_size(&body).map_err(|_| Error::CouldNotDecodeImage)?;
+    if size.width as u64 * size.height as u64 > MAX_PIXELS {
+        return Err(Error::CouldNotDecodeImage);
+    }
+
+    // Only decode what will actually be resized.
+    if size.width as u32 <= pixels || is_animated(&body) {
+        return Ok((body, content_type, size.width as f32 / width as f32));
+    }
+
+    let image = image::load_from_memory(&body).map_err(|_| Error::CouldNotDecodeImage)?;
+
+    let (format, content_type) = if content_type == "image/jpeg" {
+        (ImageOutputFormat::Jpeg(85), content_type)
+    } else {
+        (ImageOutputFormat::Png, "image/png".to_string())
+    };
+
+    let resized = image.resize(pixels, u32::MAX, FilterType::Triangle);
+    let original = image.width() as f32 / width as f32;
+    let resized = match icc::to_srgb(&body, resized)? {
+        Some(resized) => resized,
+        None => return Ok((body, content_type, original)),
+    };
+
+    let mut output = Vec::new();
+    resized
+        .write_to(&mut output, format)
+        .map_err(|_| Error::CouldNotDecodeImage)?;
+
+    match icc::transfer(&body, output)? {
+        Some(output) => Ok((
+            Bytes::from(output),
+            content_type,
+            pixels as f32 / width as f32,
+        )),
+        None => Ok((body, content_type, original)),
+    }
+}
+
+/// Name the file after the last segment of its URL, so saving it from a
+/// client gives something sensible. The extension follows the content
+/// type, which may have changed if we re-encoded it.
+fn disposition(url: &str, content_type: &str) -> String {
+    let extension = match content_type {
+        "image/jpeg" => "jpg",
+        "image/svg+xml" => "svg",
+        other => other.rsplit('/').next().unwrap_or("bin"),
+    };
+
+    let name = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            let segment = url.path_segments()?.next_back()?.to_string();
+            Some(percent_decode_str(&segment).decode_utf8_lossy().to_string())
+        })
+        .map(|name| {
+            name.chars()
+                .filter(|c| !c.is_control() && !matches!(c, '"' | '\\' | '/'))
+                .collect::<String>()
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "image".to_string());
+
+    let stem = match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => &name,
+    };
+
+    let filename = format!("{}.{}", stem, extension);
+    let ascii: String = filename
+        .chars()
+        .map(|c| if c.is_ascii() { c } else { '_' })
+        .collect();
+
+    format!(
+        "inline; filename=\"{}\"; filename*=UTF-8''{}",
+        ascii,
+        utf8_percent_encode(&filename, FILENAME)
+    )
 }
 
-pub async fn get(info: Query<Parameters>) -> Result<impl Responder, Error> {
-    let url = info.into_inner().url;
-    let (resp, mime) = fetch(&url).await?;
+/// Start a response for proxied media. Headers from the origin are never
+/// passed through, so cookies and reporting endpoints can't reach clients,
+/// and the content is locked down in case it's opened directly.
+pub fn respond(url: &str, content_type: &str) -> HttpResponseBuilder {
+    let mut builder = HttpResponse::Ok();
+    builder
+        // Media is already compressed, leave it be.
+        .encoding(ContentEncoding::Identity)
+        .content_type(content_type)
+        .insert_header((CONTENT_DISPOSITION, disposition(url, content_type)))
+        .insert_header((X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .insert_header(("Cross-Origin-Resource-Policy", "cross-origin"))
+        // SVGs can carry scripts.
+        .insert_header((
+            CONTENT_SECURITY_POLICY,
+            "default-src 'none'; style-src 'unsafe-inline'; sandbox",
+        ));
+
+    builder
+}
+
+/// Re-encode formats which no browser can display. Like `render`
+/// this blocks.
+fn convert(body: Bytes, content_type: String) -> Result<(Bytes, String), Error> {
+    #[cfg(feature = "heic")]
+    if content_type == "image/heic" || content_type == "image/heif" {
+        let body = crate::util::heic::to_jpeg(&body)?;
+        return Ok((Bytes::from(body), "image/jpeg".to_string()));
+    }
+
+    Ok((body, content_type))
+}
+
+/// The image to serve, with JPEG XL left alone for clients which say
+/// they support it and decoded for those which don't.
+async fn original(
+    url: String,
+    #[cfg_attr(not(feature = "jxl"), allow(unused_variables))] accepts_jxl: bool,
+) -> Result<(Bytes, String), Error> {
+    let original = source(url.clone()).await?;
+
+    #[cfg(feature = "jxl")]
+    if original.1 == "image/jxl" && !accepts_jxl {
+        if let Some(decoded) = DECODED.get(&url) {
+            return Ok(decoded);
+        }
+
+        let body = original.0;
+        let decoded = web::block(move || crate::util::jxl::to_png(&body))
+            .await
+            .map_err(|_| Error::ConversionFailed)??;
+        let decoded = (Bytes::from(decoded), "image/png".to_string());
+        DECODED.insert(url, decoded.clone());
+        return Ok(decoded);
+    }
+
+    Ok(original)
+}
+
+/// The image at `url` as it was fetched, asking upstream whether it
+/// has changed once the cached copy expires.
+async fn source(url: String) -> Result<(Bytes, String), Error> {
+    let cached = CACHE.get_stale(&url);
+    if let Some((original, true)) = cached {
+        return Ok((original.body, original.content_type));
+    }
+
+    let _permit = load::admit().await?;
+    let stale = cached.map(|(original, _)| original);
+    let validators = stale.as_ref().map(Original::validators).unwrap_or_default();
+    let (resp, mime) = match refetch_image(&url, &validators).await? {
+        Some(fetched) => fetched,
+        None => {
+            let original = stale.ok_or(Error::RequestFailed)?;
+            CACHE.insert(url, original.clone());
+            return Ok((original.body, original.content_type));
+        }
+    };
 
     if let mime::IMAGE = mime.type_() {
-        let body = resp
-            .bytes()
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+
+        let body = consume_bytes(resp, *MAX_IMAGE_SIZE + 1).await?;
+        if body.len() > *MAX_IMAGE_SIZE {
+            return Err(Error::ImageTooLarge);
+        }
+
+        // Without parameters, so the type can be compared and named after.
+        let content_type = mime.essence_str().to_string();
+        let (body, content_type) = web::block(move || convert(Bytes::from(body), content_type))
             .await
-            .map_err(|_| Error::FailedToConsumeBytes)?;
-        Ok(HttpResponse::Ok().body(body))
+            .map_err(|_| Error::ConversionFailed)??;
+        CACHE.insert(
+            url,
+            Original {
+                body: body.clone(),
+                content_type: content_type.clone(),
+                etag,
+                last_modified,
+            },
+        );
+        Ok((body, content_type))
     } else {
         Err(Error::NotAllowedToProxy)
     }
 }
+
+pub async fn get(req: HttpRequest, info: Query<Parameters>) -> Result<impl Responder, Error> {
+    let Parameters {
+        url,
+        width,
+        dpr,
+        format,
+    } = info.into_inner();
+    let accepts_jxl = req
+        .headers()
+        .get(ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains("image/jxl"))
+        .unwrap_or(false);
+
+    let url = validate_url(&url)?;
+    if opted_out(&url).await {
+        return Err(Error::NotAllowedToProxy);
+    }
+
+    bandwidth::check(&req)?;
+
+    if format.as_deref() == Some("mp4") && FFMPEG.is_some() {
+        let (body, content_type) = original(url.clone(), false).await?;
+        if content_type == "image/gif" && body.len() >= *GIF_TRANSCODE_SIZE {
+            let video = ffmpeg::gif_to_mp4(&url, body.to_vec()).await?;
+            bandwidth::record(&req, &url, video.len());
+            return Ok(respond(&url, "video/mp4").body(video));
+        }
+    }
+
+    let width = match width {
+        Some(width) if width > 0 => width.min(4096),
+        _ => {
+            let (body, content_type) = original(url.clone(), accepts_jxl).await?;
+            bandwidth::record(&req, &url, body.len());
+            return Ok(respond(&url, &content_type)
+                .insert_header((VARY, "Accept"))
+                .body(body));
+        }
+    };
+
+    let dpr = dpr
+        .filter(|dpr| dpr.is_finite())
+        .unwrap_or(1.0)
+        .clamp(1.0, 4.0);
+    let pixels = (width as f32 * dpr).round() as u32;
+    let key = (url.clone(), width, pixels);
+    let (body, content_type, density) = match RENDITIONS.get(&key) {
+        Some(rendition) => rendition,
+        None => {
+            // Renditions are always re-encoded, so need a format we can decode.
+            let original = original(url.clone(), false).await?;
+            let rendition = web::block(move || render(original, width, pixels))
+                .await
+                .map_err(|_| Error::CouldNotDecodeImage)??;
+            RENDITIONS.insert(key, rendition.clone());
+            rendition
+        }
+    };
+
+    bandwidth::record(&req, &url, body.len());
+    Ok(respond(&url, &content_type)
+        .insert_header(("Content-DPR", format!("{:.2}", density)))
+        .body(body))
+}
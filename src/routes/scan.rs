@@ -0,0 +1,104 @@
+use actix_web::http::StatusCode;
+use actix_web::{web::Json, HttpResponse, Responder};
+use futures::future::join_all;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::structs::embed::Embed;
+use crate::util::result::Error;
+use crate::util::variables::MAX_EMBEDS_PER_REQUEST;
+
+use super::embed::embed;
+
+lazy_static! {
+    static ref RE_CODE: Regex = Regex::new("(?s)```.*?```|`[^`\n]*`").unwrap();
+    static ref RE_URL: Regex = Regex::new("<?https?://[^\\s<>]*[^\\s<>.,:;\"'!?)\\]]>?").unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_CODE, &RE_URL];
+    patterns.len()
+}
+
+#[derive(Deserialize)]
+pub struct Body {
+    content: String,
+}
+
+/// How a single link turned out, so one bad link doesn't fail the rest.
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Ok(Box<Embed>),
+    Err(Error),
+}
+
+#[derive(Serialize)]
+pub struct Item {
+    url: String,
+    #[serde(flatten)]
+    outcome: Outcome,
+}
+
+#[derive(Serialize)]
+pub struct Scan {
+    results: Vec<Item>,
+    // Links past the cap which were not looked at.
+    truncated: usize,
+}
+
+/// Pull out the links a Revolt client would embed from a message, in the
+/// order they appear. Code is skipped, as are links wrapped in `<>` which
+/// users write to suppress the embed.
+pub fn extract_urls(content: &str) -> Vec<String> {
+    let content = RE_CODE.replace_all(content, "");
+    let mut urls: Vec<String> = Vec::new();
+    for found in RE_URL.find_iter(&content) {
+        let url = found.as_str();
+        if url.starts_with('<') && url.ends_with('>') {
+            continue;
+        }
+
+        let url = url.trim_start_matches('<').trim_end_matches('>');
+        if !urls.iter().any(|existing| existing == url) {
+            urls.push(url.to_string());
+        }
+    }
+
+    urls
+}
+
+pub async fn post(body: Json<Body>) -> Result<impl Responder, Error> {
+    let mut urls = extract_urls(&body.content);
+    let truncated = urls.len().saturating_sub(*MAX_EMBEDS_PER_REQUEST);
+    urls.truncate(*MAX_EMBEDS_PER_REQUEST);
+
+    let embeds = join_all(urls.iter().cloned().map(embed)).await;
+    let results: Vec<Item> = urls
+        .into_iter()
+        .zip(embeds)
+        .map(|(url, result)| {
+            // Links with nothing to embed are kept as `None`, so
+            // clients can still match every result to its link.
+            let outcome = match result {
+                Ok(embed) => Outcome::Ok(Box::new(embed)),
+                Err(error) => Outcome::Err(error),
+            };
+
+            Item { url, outcome }
+        })
+        .collect();
+
+    // Multi-Status tells clients to look at each result.
+    let status = if results
+        .iter()
+        .any(|item| matches!(item.outcome, Outcome::Err(_)))
+    {
+        StatusCode::MULTI_STATUS
+    } else {
+        StatusCode::OK
+    };
+
+    Ok(HttpResponse::build(status).json(Scan { results, truncated }))
+}
@@ -0,0 +1,37 @@
+use actix_web::{web::Query, HttpRequest, Responder};
+use serde::Deserialize;
+
+use crate::routes::proxy::respond;
+use crate::util::opt_out::opted_out;
+use crate::util::request::validate_url;
+use crate::util::result::Error;
+use crate::util::{bandwidth, ffmpeg};
+
+// Posters are cached per second of offset, so the offsets asked for are
+// bounded to keep one video from filling the cache.
+const MAX_OFFSET: f64 = 3600.0;
+
+#[derive(Deserialize)]
+pub struct Parameters {
+    url: String,
+    // Offset into the video in seconds.
+    t: Option<f64>,
+}
+
+pub async fn get(req: HttpRequest, info: Query<Parameters>) -> Result<impl Responder, Error> {
+    let Parameters { url, t } = info.into_inner();
+    let url = validate_url(&url)?;
+    if opted_out(&url).await {
+        return Err(Error::NotAllowedToProxy);
+    }
+
+    bandwidth::check(&req)?;
+
+    let timestamp = t
+        .filter(|t| t.is_finite() && *t >= 0.0)
+        .unwrap_or(0.0)
+        .min(MAX_OFFSET) as u32;
+    let body = ffmpeg::poster(&url, timestamp).await?;
+    bandwidth::record(&req, &url, body.len());
+    Ok(respond(&url, "image/jpeg").body(body))
+}
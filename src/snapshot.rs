@@ -0,0 +1,176 @@
+use bumpalo::Bump;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::structs::metadata::Metadata;
+use crate::util::head;
+use crate::util::request::{consume_head, fetch};
+
+// Where the fixtures live, relative to the working directory.
+const DEFAULT_DIR: &str = "snapshots";
+
+/// Parse a stored page the way an embed would be, before anything
+/// touches the network. Fixtures start with a comment naming the URL
+/// the page was saved from, so relative links resolve as they did.
+fn parse(html: &str) -> Result<Value, String> {
+    let url = html
+        .trim_start()
+        .strip_prefix("<!--")
+        .and_then(|rest| rest.split("-->").next())
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .ok_or("fixture doesn't start with a <!-- url --> comment")?;
+
+    let arena = Bump::new();
+    let document = head::scan(html, &arena);
+    let metadata = Metadata::from_document(&document, url.to_string(), None)
+        .map_err(|error| format!("{:?}", error))?;
+    let value = serde_json::to_value(&metadata).map_err(|error| error.to_string())?;
+
+    // Clients read embeds through january-types, so it must take
+    // back exactly what was served.
+    let parsed: january_types::Metadata =
+        serde_json::from_value(value.clone()).map_err(|error| error.to_string())?;
+    if serde_json::to_value(&parsed).ok().as_ref() != Some(&value) {
+        return Err("changes when read back through january-types".to_string());
+    }
+
+    Ok(value)
+}
+
+/// Lines only in the snapshot are marked `-`, lines only in the new
+/// output `+`. Enough to review a change without a diffing crate.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    let removed = expected
+        .iter()
+        .filter(|line| !actual.contains(line))
+        .map(|line| format!("    - {}", line));
+    let added = actual
+        .iter()
+        .filter(|line| !expected.contains(line))
+        .map(|line| format!("    + {}", line));
+
+    removed.chain(added).collect::<Vec<_>>().join("\n")
+}
+
+fn compare(fixture: &Path, update: bool) -> Result<String, String> {
+    let html = fs::read_to_string(fixture).map_err(|error| error.to_string())?;
+    let actual = serde_json::to_string_pretty(&parse(&html)?).unwrap() + "\n";
+
+    let snapshot = fixture.with_extension("json");
+    if update {
+        fs::write(&snapshot, &actual).map_err(|error| error.to_string())?;
+        return Ok("updated".to_string());
+    }
+
+    let expected = fs::read_to_string(&snapshot)
+        .map_err(|_| "no snapshot yet, run with --update to write one".to_string())?;
+    if expected == actual {
+        Ok("matches".to_string())
+    } else {
+        Err(format!(
+            "differs from the snapshot\n{}",
+            diff(&expected, &actual)
+        ))
+    }
+}
+
+/// Save a live page as a new fixture along with its snapshot, for
+/// `january snapshot --record <name> <url> [dir]`. Only the head is
+/// kept, as nothing after it is read.
+pub async fn record(args: &[String]) -> i32 {
+    let (name, url) = match args {
+        [name, url, ..] => (name, url),
+        _ => {
+            println!("usage: january snapshot --record <name> <url> [dir]");
+            return 1;
+        }
+    };
+    let dir = args
+        .get(2)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_DIR));
+
+    let html = match fetch(url).await {
+        Ok((resp, _)) => consume_head(resp).await,
+        Err(error) => Err(error),
+    };
+    let html = match html {
+        Ok(html) => html,
+        Err(error) => {
+            println!("FAIL could not fetch {}: {:?}", url, error);
+            return 1;
+        }
+    };
+
+    let fixture = dir.join(name).with_extension("html");
+    let result = fs::write(&fixture, format!("<!-- {} -->\n{}\n", url, html))
+        .map_err(|error| error.to_string())
+        .and_then(|_| compare(&fixture, true));
+    match result {
+        Ok(_) => {
+            println!("ok   {:<12} recorded", name);
+            0
+        }
+        Err(detail) => {
+            println!("FAIL {:<12} {}", name, detail);
+            1
+        }
+    }
+}
+
+/// Compare every fixture against its stored metadata for
+/// `january snapshot [--update] [dir]`, returning the exit code.
+pub fn run(args: &[String]) -> i32 {
+    let update = args.iter().any(|arg| arg == "--update");
+    let dir = args
+        .iter()
+        .find(|arg| !arg.starts_with("--"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_DIR));
+
+    let mut fixtures: Vec<PathBuf> = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension() == Some("html".as_ref()))
+            .collect(),
+        Err(error) => {
+            println!("FAIL could not read {}: {}", dir.display(), error);
+            return 1;
+        }
+    };
+    fixtures.sort();
+
+    let mut passed = true;
+    for fixture in &fixtures {
+        let name = fixture.file_stem().unwrap_or_default().to_string_lossy();
+        match compare(fixture, update) {
+            Ok(detail) => println!("ok   {:<12} {}", name, detail),
+            Err(detail) => {
+                println!("FAIL {:<12} {}", name, detail);
+                passed = false;
+            }
+        }
+    }
+
+    if passed {
+        0
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixtures_match_snapshots() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_DIR);
+        assert_eq!(run(&[dir.to_string_lossy().to_string()]), 0);
+    }
+}
@@ -0,0 +1,98 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Event {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timezone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<String>,
+}
+
+/// Undo TEXT escaping in a single pass, so an escaped backslash
+/// followed by `n` stays a backslash and an `n`.
+fn unescape(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') | Some('N') => output.push('\n'),
+            Some(escaped) => output.push(escaped),
+            None => output.push('\\'),
+        }
+    }
+
+    output
+}
+
+impl Event {
+    /// Read the first VEVENT out of an iCalendar document.
+    pub fn from(calendar: &str) -> Option<Event> {
+        // Long lines are folded onto continuation lines starting with whitespace.
+        let unfolded = calendar
+            .replace("\r\n", "\n")
+            .replace("\n ", "")
+            .replace("\n\t", "");
+
+        let mut event = None;
+        for line in unfolded.lines() {
+            if line == "BEGIN:VEVENT" {
+                event = Some(Event::default());
+                continue;
+            }
+
+            let current = match event.as_mut() {
+                Some(current) => current,
+                None => continue,
+            };
+
+            if line == "END:VEVENT" {
+                break;
+            }
+
+            let (property, value) = match line.split_once(':') {
+                Some(pair) => pair,
+                None => continue,
+            };
+
+            let mut params = property.split(';');
+            let name = params.next().unwrap_or_default();
+            match name {
+                "SUMMARY" => current.summary = Some(unescape(value)),
+                "LOCATION" => current.location = Some(unescape(value)),
+                "DTSTART" => {
+                    current.start = Some(value.to_string());
+                    current.timezone = params
+                        .find_map(|param| param.strip_prefix("TZID="))
+                        .map(str::to_string);
+                }
+                "DTEND" => current.end = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescapes_in_one_pass() {
+        assert_eq!(unescape(r"a\, b\; c\nd"), "a, b; c\nd");
+        assert_eq!(unescape(r"C:\\new"), r"C:\new");
+        assert_eq!(unescape(r"trailing\"), r"trailing\");
+    }
+}
@@ -1,11 +1,38 @@
 use serde::Serialize;
 
-use super::{media::Image, metadata::Metadata};
+use super::{
+    calendar::Event,
+    media::{Image, Video},
+    metadata::Metadata,
+    route::Route,
+};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type")]
+#[allow(clippy::large_enum_variant)]
 pub enum Embed {
     Website(Metadata),
     Image(Image),
+    Video(Video),
+    Event(Event),
+    Route(Route),
     None,
 }
+
+impl Embed {
+    /// Whether anything went wrong while generating the embed, which
+    /// may well have been temporary.
+    pub fn has_warnings(&self) -> bool {
+        match self {
+            Embed::Website(metadata) => metadata.has_warnings(),
+            _ => false,
+        }
+    }
+
+    pub fn is_dangerous(&self) -> bool {
+        match self {
+            Embed::Website(metadata) => metadata.is_dangerous(),
+            _ => false,
+        }
+    }
+}
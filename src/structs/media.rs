@@ -1,22 +1,13 @@
-use serde::Serialize;
+pub use january_types::media::{Image, ImageSize, Video};
 
-#[derive(Debug, Serialize)]
-pub enum ImageSize {
-    Large,
-    Preview,
-}
-
-#[derive(Debug, Serialize)]
-pub struct Image {
-    pub url: String,
-    pub width: isize,
-    pub height: isize,
-    pub size: ImageSize,
-}
+use crate::util::request::Probe;
 
-#[derive(Debug, Serialize)]
-pub struct Video {
-    pub url: String,
-    pub width: isize,
-    pub height: isize,
+/// Fill in what was learnt from fetching the image itself.
+pub fn probed(mut image: Image, probe: Probe) -> Image {
+    image.width = probe.width;
+    image.height = probe.height;
+    image.animated = probe.animated;
+    image.size_bytes = probe.size_bytes;
+    image.format = probe.format.map(str::to_string);
+    image
 }
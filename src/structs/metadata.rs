@@ -1,85 +1,493 @@
+use bumpalo::Bump;
+use futures::join;
 use regex::Regex;
-use reqwest::Response;
-use scraper::Selector;
-use serde::Serialize;
-use std::collections::HashMap;
+use reqwest::{header::LINK, Response, Url};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
 
-use crate::{structs::special::{BandcampType, TwitchType}, util::{
-        request::{consume_fragment, consume_size, fetch},
+use january_types::{Snapshot, Warning};
+
+use crate::providers::{
+    activitypub, archive, artwork, bluesky, campaign, data, discord, drive, facebook, github,
+    gitlab, imgur, instagram, itch, manifest, mastodon, matrix, paste, photo, reddit, slides,
+    songlink, steam, tiktok, twitch, twitter, wikipedia,
+};
+use crate::{
+    structs::special::{AppleMusicType, BandcampType, GifProvider, PasteProvider, TwitchType},
+    util::{
+        colour, ffmpeg,
+        head::{self, Head},
+        metrics,
+        referer::{self, fetch_image},
+        reputation::Threat,
+        request::{
+            consume_bytes, consume_colour, consume_head, consume_size, decode_data_uri,
+            dominant_colour, fetch_with_header, image_size, resolve_url,
+        },
         result::Error,
-    }};
+        variables::{
+            MetaSource, IFRAME_PROVIDERS, MAX_DATA_URI_SIZE, META_PRECEDENCE, OPENWEATHERMAP_KEY,
+            SONGLINK_RESOLVE, STOCK_EMBEDS,
+        },
+    },
+};
+
+use super::{
+    media::{self, Image, ImageSize, Video},
+    special::Special,
+};
+
+lazy_static! {
+    // ! FIXME: use youtube-dl to fetch metadata
+    static ref RE_YOUTUBE: Regex = Regex::new("^(?:(?:https?:)?//)?(?:(?:www|m|music)\\.)?(?:(?:youtube\\.com|youtube-nocookie\\.com|youtu.be))(?:/(?:[\\w\\-]+\\?v=|embed/|v/)?)([\\w\\-]+)(?:\\S+)?$").unwrap();
+    static ref RE_YOUTUBE_ATTRIBUTION: Regex = Regex::new("^(?:https?://)?(?:www\\.|m\\.)?youtube\\.com/attribution_link\\?(?:\\S*&)?u=(?:/|%2F)watch(?:\\?|%3F)v(?:=|%3D)([\\w\\-]+)").unwrap();
+
+    static ref RE_VIMEO: Regex = Regex::new("^(?:https?://)?(?:www\\.)?vimeo\\.com/(?:channels/[\\w-]+/|groups/[\\w-]+/videos/|(?:album|showcase)/\\d+/video/)?(\\d+)(?:/([0-9a-f]+))?(?:$|[/?#])").unwrap();
+    static ref RE_VIMEO_PLAYER: Regex = Regex::new("^(?:https?://)?player\\.vimeo\\.com/video/(\\d+)(?:\\?(?:\\S*&)?h=([0-9a-f]+))?").unwrap();
+    static ref RE_DAILYMOTION: Regex = Regex::new("^(?:https?://)?(?:(?:www\\.)?dailymotion\\.com/(?:embed/)?video/|dai\\.ly/)([a-zA-Z0-9]+)").unwrap();
+
+    // ! FIXME: use Twitch API to fetch metadata
+    static ref RE_TWITCH: Regex = Regex::new("^(?:https?://)?(?:www\\.|go\\.)?twitch\\.tv/([a-z0-9_]+)($|\\?)").unwrap();
+    static ref RE_TWITCH_VOD: Regex = Regex::new("^(?:https?://)?(?:www\\.|go\\.)?twitch\\.tv/videos/([0-9]+)($|\\?)").unwrap();
+    static ref RE_TWITCH_CATEGORY: Regex = Regex::new("^(?:https?://)?(?:www\\.|go\\.)?twitch\\.tv/directory/(?:game|category)/([^/?#]+)").unwrap();
+    static ref RE_TWITCH_CLIP: Regex = Regex::new("^(?:https?://)?(?:www\\.|go\\.)?twitch\\.tv/(?:[a-z0-9_]+)/clip/([A-z0-9_-]+)($|\\?)").unwrap();
+
+    static ref RE_SPOTIFY: Regex = Regex::new("^(?:https?://)?open.spotify.com/(track|user|artist|album|playlist)/([A-z0-9]+)").unwrap();
+    static ref RE_DEEZER: Regex = Regex::new("^(?:https?://)?(?:www\\.)?deezer\\.com/(?:[a-z]{2}(?:-[a-z]{2})?/)?(track|album|playlist|artist|show|episode)/(\\d+)").unwrap();
+    static ref RE_DEEZER_SHORT: Regex = Regex::new("^(?:https?://)?(?:deezer\\.page\\.link|link\\.deezer\\.com)/\\S+").unwrap();
+    static ref RE_TIDAL: Regex = Regex::new("^(?:https?://)?(?:www\\.|listen\\.|embed\\.)?tidal\\.com/(?:browse/)?(track|album|playlist|video)/([0-9a-f-]+)").unwrap();
+    static ref RE_MIXCLOUD: Regex = Regex::new("^(?:https?://)?(?:www\\.|m\\.)?mixcloud\\.com/([\\w.-]+)/([\\w.-]+)/?(?:$|[?#])").unwrap();
+    static ref RE_APPLE_MUSIC: Regex = Regex::new("^(?:https?://)?(?:embed\\.)?music\\.apple\\.com/([a-z]{2})/(album|song|playlist)/(?:[^/?#]+/)?([\\w.-]+)").unwrap();
+    static ref RE_SONGLINK: Regex = Regex::new("^(?:https?://)?(?:(?:song|album|artist|playlist|pods)\\.link|odesli\\.co)/\\S+").unwrap();
+    static ref RE_SOUNDCLOUD: Regex = Regex::new("^(?:https?://)?soundcloud.com/([a-zA-Z0-9-]+)/([A-z0-9-]+)").unwrap();
+    static ref RE_BANDCAMP: Regex = Regex::new("^(?:https?://)?(?:[A-z0-9_-]+).bandcamp.com/(track|album)/([A-z0-9_-]+)").unwrap();
+
+    static ref RE_MATRIX: Regex = Regex::new("^(?:https?://)?matrix\\.to/#/([#!][^/?\\s]+:[^/?\\s]+)(?:/(\\$[^/?\\s]+))?").unwrap();
+    static ref RE_DISCORD_INVITE: Regex = Regex::new("^(?:https?://)?(?:www\\.)?(?:discord\\.gg|(?:discord|discordapp)\\.com/invite)/([A-Za-z0-9-]+)").unwrap();
+    static ref RE_INSTAGRAM: Regex = Regex::new("^(?:https?://)?(?:www\\.)?instagram\\.com/(?:[\\w.]+/)?(?:p|reel|tv)/([A-Za-z0-9_-]+)").unwrap();
+    static ref RE_TIKTOK: Regex = Regex::new("^(?:https?://)?(?:www\\.|m\\.)?tiktok\\.com/(?:@[\\w.-]+/video/|embed/(?:v2/)?|v/)(\\d+)").unwrap();
+    static ref RE_TUMBLR: Regex = Regex::new("^(?:https?://)?([\\w-]+)\\.tumblr\\.com/post/(\\d+)").unwrap();
+    static ref RE_TUMBLR_DASHBOARD: Regex = Regex::new("^(?:https?://)?(?:www\\.)?tumblr\\.com/(?:blog/view/)?([\\w-]+)/(\\d+)").unwrap();
+    static ref RE_DEVIANTART: Regex = Regex::new("^(?:https?://)?(?:(?:www\\.)?deviantart\\.com/[\\w-]+|[\\w-]+\\.deviantart\\.com)/art/[\\w-]+").unwrap();
+    static ref RE_ARTSTATION: Regex = Regex::new("^(?:https?://)?(?:www\\.)?artstation\\.com/artwork/([A-Za-z0-9]+)").unwrap();
+    static ref RE_FLICKR: Regex = Regex::new("^(?:https?://)?(?:www\\.)?(?:flickr\\.com/photos/[\\w@-]+/\\d+|flic\\.kr/p/\\w+)").unwrap();
+    static ref RE_UNSPLASH: Regex = Regex::new("^(?:https?://)?(?:www\\.)?unsplash\\.com/photos/[\\w-]+").unwrap();
+    static ref RE_SPEAKERDECK: Regex = Regex::new("^(?:https?://)?(?:www\\.)?speakerdeck\\.com/[\\w-]+/[\\w-]+").unwrap();
+    static ref RE_SLIDESHARE: Regex = Regex::new("^(?:https?://)?(?:[a-z]{2,3}\\.|www\\.)?slideshare\\.net/(?:slideshow/)?[\\w-]+/[\\w-]+").unwrap();
+    static ref RE_ARCHIVE: Regex = Regex::new("^(?:https?://)?(?:www\\.)?archive\\.org/(?:details|embed)/([\\w.-]+)").unwrap();
+    static ref RE_DROPBOX: Regex = Regex::new("^(?:https?://)?(?:www\\.)?dropbox\\.com/(?:s|scl/fi)/[\\w-]+/([^?#/]+)").unwrap();
+    static ref RE_GOOGLE_DRIVE: Regex = Regex::new("^(?:https?://)?drive\\.google\\.com/(?:file/d/|open\\?id=)([\\w-]+)").unwrap();
+    static ref RE_ONEDRIVE: Regex = Regex::new("^(?:https?://)?(?:1drv\\.ms|onedrive\\.live\\.com)/\\S+").unwrap();
+    static ref RE_PASTEBIN: Regex = Regex::new("^(?:https?://)?(?:www\\.)?pastebin\\.com/(?:raw/)?([A-Za-z0-9]{8})(?:$|[?#])").unwrap();
+    static ref RE_GIST: Regex = Regex::new("^(?:https?://)?gist\\.github\\.com/(?:([\\w-]+)/)?([0-9a-f]+)").unwrap();
+    static ref RE_HASTEBIN: Regex = Regex::new("^(?:https?://)?((?:www\\.)?(?:hastebin\\.com|hst\\.sh))/(?:raw/)?([a-z]+)(?:\\.(\\w+))?(?:$|[?#])").unwrap();
+    static ref RE_ITCH: Regex = Regex::new("^(?:https?://)?[\\w-]+\\.itch\\.io/[\\w-]+/?(?:$|\\?)").unwrap();
+    static ref RE_KICKSTARTER: Regex = Regex::new("^(?:https?://)?(?:www\\.)?kickstarter\\.com/projects/([\\w-]+/[\\w-]+)").unwrap();
+    static ref RE_PATREON: Regex = Regex::new("^(?:https?://)?(?:www\\.)?patreon\\.com/(?:c/)?([\\w-]+)/?(?:$|\\?)").unwrap();
+    static ref RE_YAHOO_FINANCE: Regex = Regex::new("^(?:https?://)?(?:[a-z]{2}\\.)?finance\\.yahoo\\.com/quote/([\\w.^=-]+)").unwrap();
+    static ref RE_OPENWEATHERMAP: Regex = Regex::new("^(?:https?://)?(?:www\\.)?openweathermap\\.org/city/(\\d+)").unwrap();
+
+    static ref RE_GIPHY: Regex = Regex::new("^(?:https?://)?(?:www\\.)?giphy\\.com/(?:gifs|stickers)/(?:[\\w-]+-)?([A-Za-z0-9]+)/?(?:$|\\?)").unwrap();
+    static ref RE_TENOR: Regex = Regex::new("^(?:https?://)?(?:www\\.)?tenor\\.com/(?:[a-z]{2}(?:-[A-Z]{2})?/)?view/(?:[\\w-]+-)?(\\d+)").unwrap();
+
+    static ref RE_TIMESTAMP: Regex = Regex::new("(?:\\?|&)(?:t|start)=([\\w]+)").unwrap();
+
+    // Private shares carry their token either in the query or as the last path segment.
+    static ref RE_SECRET_TOKEN: Regex = Regex::new("(?:[?&]secret_token=|/)(s-[A-Za-z0-9]+)(?:$|[?&/#])").unwrap();
+
+    static ref RE_TRACK: Regex = Regex::new("track=(\\d+)").unwrap();
+    static ref RE_ALBUM: Regex = Regex::new("album=(\\d+)").unwrap();
+
+    static ref RE_DURATION: Regex = Regex::new("^(?:(\\d+)h)?(?:(\\d+)m)?(?:(\\d+)s?)?$").unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[
+        &RE_YOUTUBE,
+        &RE_YOUTUBE_ATTRIBUTION,
+        &RE_VIMEO,
+        &RE_VIMEO_PLAYER,
+        &RE_DAILYMOTION,
+        &RE_TWITCH,
+        &RE_TWITCH_VOD,
+        &RE_TWITCH_CATEGORY,
+        &RE_TWITCH_CLIP,
+        &RE_SPOTIFY,
+        &RE_DEEZER,
+        &RE_DEEZER_SHORT,
+        &RE_TIDAL,
+        &RE_APPLE_MUSIC,
+        &RE_MIXCLOUD,
+        &RE_SONGLINK,
+        &RE_SOUNDCLOUD,
+        &RE_BANDCAMP,
+        &RE_MATRIX,
+        &RE_DISCORD_INVITE,
+        &RE_INSTAGRAM,
+        &RE_TIKTOK,
+        &RE_TUMBLR,
+        &RE_TUMBLR_DASHBOARD,
+        &RE_DEVIANTART,
+        &RE_ARTSTATION,
+        &RE_FLICKR,
+        &RE_UNSPLASH,
+        &RE_SPEAKERDECK,
+        &RE_SLIDESHARE,
+        &RE_ARCHIVE,
+        &RE_DROPBOX,
+        &RE_GOOGLE_DRIVE,
+        &RE_ONEDRIVE,
+        &RE_PASTEBIN,
+        &RE_GIST,
+        &RE_HASTEBIN,
+        &RE_ITCH,
+        &RE_KICKSTARTER,
+        &RE_PATREON,
+        &RE_YAHOO_FINANCE,
+        &RE_OPENWEATHERMAP,
+        &RE_GIPHY,
+        &RE_TENOR,
+        &RE_TIMESTAMP,
+        &RE_SECRET_TOKEN,
+        &RE_TRACK,
+        &RE_ALBUM,
+        &RE_DURATION,
+    ];
+
+    patterns.len()
+}
+
+/// Tags a field is read from for each source, see [`MetaSource`].
+struct Field {
+    opengraph: &'static [&'static str],
+    twitter: &'static [&'static str],
+    meta: &'static [&'static str],
+    json_ld: &'static [&'static str],
+}
+
+const TITLE: Field = Field {
+    opengraph: &["og:title"],
+    twitter: &["twitter:title"],
+    meta: &["title"],
+    json_ld: &["headline", "name"],
+};
+
+const DESCRIPTION: Field = Field {
+    opengraph: &["og:description"],
+    twitter: &["twitter:description"],
+    meta: &["description"],
+    json_ld: &["description"],
+};
+
+const IMAGE: Field = Field {
+    opengraph: &["og:image", "og:image:secure_url"],
+    twitter: &["twitter:image", "twitter:image:src"],
+    meta: &[],
+    json_ld: &["image", "thumbnailUrl"],
+};
+
+// App Links names are usually the same brand as the site itself.
+const SITE_NAME: Field = Field {
+    opengraph: &["og:site_name"],
+    twitter: &[],
+    meta: &[
+        "application-name",
+        "apple-mobile-web-app-title",
+        "al:ios:app_name",
+        "al:android:app_name",
+    ],
+    json_ld: &[],
+};
+
+/// Read a field from the first source that has it, in the configured
+/// order, so the result doesn't depend on how tags are ordered on the page.
+fn pick(meta: &mut HashMap<&str, &str>, json_ld: &[Value], field: &Field) -> Option<String> {
+    META_PRECEDENCE.iter().find_map(|source| {
+        let keys = match source {
+            MetaSource::OpenGraph => field.opengraph,
+            MetaSource::Twitter => field.twitter,
+            MetaSource::Meta => field.meta,
+            MetaSource::JsonLd => {
+                return field.json_ld.iter().find_map(|key| {
+                    json_ld_objects(json_ld).find_map(|value| json_ld_text(&value[*key]))
+                })
+            }
+        };
+
+        keys.iter()
+            .find_map(|key| meta.remove(*key))
+            .map(str::to_string)
+    })
+}
+
+/// Every object in the page's JSON-LD, including those in a `@graph`.
+fn json_ld_objects(json_ld: &[Value]) -> impl Iterator<Item = &Value> {
+    json_ld.iter().flat_map(|value| match value {
+        Value::Array(values) => values.iter().collect::<Vec<_>>(),
+        value => match &value["@graph"] {
+            Value::Array(values) => values.iter().chain(Some(value)).collect(),
+            _ => vec![value],
+        },
+    })
+}
 
-use super::{media::{Image, ImageSize, Video}, special::Special};
+/// JSON-LD values can be given as text, an object with a URL, or a list of either.
+fn json_ld_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.trim().to_string()).filter(|text| !text.is_empty()),
+        Value::Array(values) => values.first().and_then(json_ld_text),
+        Value::Object(_) => value["url"].as_str().map(str::to_string),
+        _ => None,
+    }
+}
 
-#[derive(Debug, Serialize)]
+/// An embed as it is being built. What clients receive is the public
+/// form in `january_types`; the snapshot tests read the output back
+/// through it, so the two can't drift apart unnoticed.
+#[derive(Debug, Clone, Default)]
 pub struct Metadata {
     url: String,
+    original_url: String,
+    // Where the page was fetched from once redirects were followed.
+    final_url: Option<String>,
+    json_ld: Vec<Value>,
+    activity_url: Option<String>,
+    generator: Option<String>,
+    manifest_url: Option<String>,
+    opted_out: bool,
     special: Option<Special>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
     title: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     image: Option<Image>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     video: Option<Video>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
     opengraph_type: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     site_name: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    determiner: Option<String>,
     icon_url: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     colour: Option<String>,
+    archived: Option<Snapshot>,
+    warnings: Vec<Warning>,
+    // Flagged by a reputation check, so nothing was fetched.
+    dangerous: bool,
+    warning: Option<Threat>,
+}
+
+/// What clients see of an embed, borrowed from it rather than
+/// copied, with the same shape as `january_types::Metadata`.
+#[derive(Serialize)]
+struct View<'a> {
+    url: &'a str,
+    special: Option<&'a Special>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<&'a Image>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    video: Option<&'a Video>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    opengraph_type: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    site_name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    determiner: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon_url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    colour: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archived: Option<&'a Snapshot>,
+    #[serde(skip_serializing_if = "<[Warning]>::is_empty")]
+    warnings: &'a [Warning],
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    dangerous: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warning: Option<Threat>,
+}
+
+impl Serialize for Metadata {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        View {
+            url: &self.url,
+            special: self.special.as_ref(),
+            title: self.title.as_deref(),
+            description: self.description.as_deref(),
+            image: self.image.as_ref(),
+            video: self.video.as_ref(),
+            opengraph_type: self.opengraph_type.as_deref(),
+            site_name: self.site_name.as_deref(),
+            determiner: self.determiner.as_deref(),
+            icon_url: self.icon_url.as_deref(),
+            colour: self.colour.as_deref(),
+            archived: self.archived.as_ref(),
+            warnings: &self.warnings,
+            dangerous: self.dangerous,
+            warning: self.warning,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Dimensions of a GIF from its header alone. Giphy and Tenor
+/// often give the size of another rendition in their tags.
+async fn gif_size(url: &str) -> Option<(isize, isize)> {
+    let (resp, _) = fetch_with_header(url, "Range", "bytes=0-1023").await.ok()?;
+    // Servers may ignore the range and send the whole thing.
+    let bytes = consume_bytes(resp, 1024).await.ok()?;
+    let size = imagesize::blob_size(&bytes).ok()?;
+    Some((size.width as isize, size.height as isize))
 }
 
 impl Metadata {
+    pub fn from_special(url: String, special: Special) -> Metadata {
+        Metadata {
+            original_url: url.clone(),
+            url,
+            special: Some(special),
+            ..Default::default()
+        }
+    }
+
+    /// Embed of a link flagged by a reputation check, made without
+    /// fetching anything from it.
+    pub fn dangerous(url: String, warning: Threat) -> Metadata {
+        Metadata {
+            dangerous: true,
+            warning: Some(warning),
+            ..Metadata::from_url(url)
+        }
+    }
+
+    /// Embed carrying nothing but the link itself.
+    pub fn from_url(url: String) -> Metadata {
+        Metadata {
+            original_url: url.clone(),
+            url,
+            ..Default::default()
+        }
+    }
+
     pub async fn from(resp: Response, url: String) -> Result<Metadata, Error> {
-        let fragment = consume_fragment(resp).await?;
+        let activity_url = resp
+            .headers()
+            .get_all(LINK)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .find(|v| v.contains(activitypub::ACTIVITY_JSON) && v.contains("alternate"))
+            .and_then(|v| v.split(';').next())
+            .map(|v| {
+                v.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            });
+
+        let final_url = resp.url().to_string();
 
-        let meta_selector = Selector::parse("meta").map_err(|_| Error::MetaSelectionFailed)?;
-        let mut meta = HashMap::new();
-        for el in fragment.select(&meta_selector) {
-            let node = el.value();
+        // Everything scanned out of the document is allocated together
+        // and freed at once when the arena goes out of scope.
+        let document = consume_head(resp).await?;
+        let arena = Bump::new();
+        let head = head::scan(&document, &arena);
+        Ok(Metadata {
+            final_url: Some(final_url),
+            ..Metadata::from_document(&head, url, activity_url)?
+        })
+    }
 
+    /// Read the metadata out of an already scanned document.
+    pub fn from_document(
+        head: &Head,
+        url: String,
+        activity_url: Option<String>,
+    ) -> Result<Metadata, Error> {
+        // Attributes are borrowed from the scanned document, only the
+        // values that end up in the embed are copied out of it.
+        //
+        // Names are matched case-insensitively and values are trimmed.
+        // When a property is repeated the first non-empty one wins,
+        // as OpenGraph readers conventionally take the first.
+        let mut meta: HashMap<&str, &str> = HashMap::new();
+        for tag in head.tags.iter().filter(|tag| tag.name == "meta") {
             if let (Some(property), Some(content)) = (
-                node.attr("property").or(node.attr("name")),
-                node.attr("content"),
+                tag.attr("property").or_else(|| tag.attr("name")),
+                tag.attr("content").map(str::trim),
             ) {
-                meta.insert(property.to_string(), content.to_string());
+                if !content.is_empty() {
+                    meta.entry(head.lowercase(property.trim()))
+                        .or_insert(content);
+                }
             }
         }
 
-        let link_selector = Selector::parse("link").map_err(|_| Error::MetaSelectionFailed)?;
-        let mut link = HashMap::new();
-        for el in fragment.select(&link_selector) {
-            let node = el.value();
+        // Links can carry several space separated relations, such as `shortcut icon`.
+        let mut link: HashMap<&str, &str> = HashMap::new();
+        for tag in head.tags.iter().filter(|tag| tag.name == "link") {
+            if let (Some(rel), Some(href)) = (tag.attr("rel"), tag.attr("href").map(str::trim)) {
+                if href.is_empty() {
+                    continue;
+                }
 
-            if let (Some(property), Some(content)) = (node.attr("rel"), node.attr("href")) {
-                link.insert(property.to_string(), content.to_string());
+                for relation in rel.split_ascii_whitespace() {
+                    link.entry(head.lowercase(relation)).or_insert(href);
+                }
             }
         }
 
+        // Any ActivityPub server, regardless of software, should advertise
+        // the object in either the Link header or a <link> tag.
+        let activity_url = activity_url.or_else(|| {
+            head.tags
+                .iter()
+                .filter(|tag| tag.name == "link")
+                .filter(|tag| tag.attr("rel") == Some("alternate"))
+                .filter(|tag| tag.attr("type") == Some(activitypub::ACTIVITY_JSON))
+                .find_map(|tag| tag.attr("href"))
+                .map(str::to_string)
+        });
+
+        let json_ld: Vec<Value> = head
+            .json_ld
+            .iter()
+            .filter_map(|text| serde_json::from_str(text).ok())
+            .collect();
+
+        // Relative links are resolved against <base href> when the page sets one.
+        let base = head
+            .tags
+            .iter()
+            .filter(|tag| tag.name == "base")
+            .find_map(|tag| tag.attr("href"))
+            .and_then(|href| resolve_url(&url, href))
+            .unwrap_or_else(|| url.clone());
+
+        let manifest_url = link
+            .remove("manifest")
+            .and_then(|href| resolve_url(&base, href));
+
+        // Site owners can ask not to be embedded with <meta name="january" content="noembed">.
+        let opted_out = meta
+            .remove("january")
+            .map(|v| v.split(',').any(|v| v.trim() == "noembed"))
+            .unwrap_or(false);
+
         Ok(Metadata {
-            title: meta
-                .remove("og:title")
-                .or_else(|| meta.remove("twitter:title"))
-                .or_else(|| meta.remove("title")),
-            description: meta
-                .remove("og:description")
-                .or_else(|| meta.remove("twitter:description"))
-                .or_else(|| meta.remove("description")),
-            image: meta
-                .remove("og:image")
-                .or_else(|| meta.remove("og:image:secure_url"))
-                .or_else(|| meta.remove("twitter:image"))
-                .or_else(|| meta.remove("twitter:image:src"))
+            title: pick(&mut meta, &json_ld, &TITLE),
+            description: pick(&mut meta, &json_ld, &DESCRIPTION),
+            image: pick(&mut meta, &json_ld, &IMAGE)
+                .and_then(|href| resolve_url(&base, &href))
                 .map(|url| {
                     let mut size = ImageSize::Preview;
                     if let Some(card) = meta.remove("twitter:card") {
-                        if &card == "summary_large_image" {
+                        if card == "summary_large_image" {
                             size = ImageSize::Large;
                         }
                     }
@@ -88,160 +496,791 @@ impl Metadata {
                         url,
                         width: meta
                             .remove("og:image:width")
-                            .unwrap_or_else(|| "0".to_string())
-                            .parse()
+                            .and_then(|v| v.parse().ok())
                             .unwrap_or(0),
                         height: meta
                             .remove("og:image:height")
-                            .unwrap_or_else(|| "0".to_string())
-                            .parse()
+                            .and_then(|v| v.parse().ok())
                             .unwrap_or(0),
                         size,
+                        animated: false,
+                        size_bytes: None,
+                        format: None,
                     }
                 }),
-            video: meta.remove("og:video")
+            video: meta
+                .remove("og:video")
                 .or_else(|| meta.remove("og:video:url"))
                 .or_else(|| meta.remove("og:video:secure_url"))
-                .map(|url| {
-                    Video {
-                        url,
-                        width: meta
-                            .remove("og:video:width")
-                            .unwrap_or_else(|| "0".to_string())
-                            .parse()
-                            .unwrap_or(0),
-                        height: meta
-                            .remove("og:video:height")
-                            .unwrap_or_else(|| "0".to_string())
-                            .parse()
-                            .unwrap_or(0),
-                    }
+                .and_then(|href| resolve_url(&base, href))
+                .map(|url| Video {
+                    url,
+                    width: meta
+                        .remove("og:video:width")
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(0),
+                    height: meta
+                        .remove("og:video:height")
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(0),
+                    framerate: None,
+                    audio: None,
                 }),
             icon_url: link
                 .remove("apple-touch-icon")
                 .or_else(|| link.remove("icon"))
-                .map(|mut v| {
-                    // If relative URL, prepend root URL.
-                    if let Some(ch) = v.chars().nth(0) {
-                        if ch == '/' {
-                            v = format!("{}{}", &url, v);
-                        }
-                    }
-
-                    v
+                .and_then(|href| resolve_url(&base, href)),
+            colour: meta
+                .remove("theme-color")
+                .and_then(colour::normalise)
+                .or_else(|| {
+                    meta.remove("msapplication-tilecolor")
+                        .and_then(colour::normalise)
                 }),
-            colour: meta.remove("theme-color"),
-            opengraph_type: meta.remove("og:type"),
-            site_name: meta.remove("og:site_name"),
-            url: meta.remove("og:url").unwrap_or(url),
+            opengraph_type: meta.remove("og:type").map(str::to_string),
+            site_name: pick(&mut meta, &json_ld, &SITE_NAME),
+            determiner: meta
+                .remove("og:determiner")
+                .filter(|v| !v.is_empty())
+                .map(str::to_string),
+            original_url: url.clone(),
+            final_url: None,
+            json_ld,
+            activity_url,
+            generator: meta.remove("generator").map(str::to_string),
+            manifest_url,
+            opted_out,
+            url: meta.remove("og:url").map(str::to_string).unwrap_or(url),
             special: None,
+            archived: None,
+            warnings: Vec::new(),
+            dangerous: false,
+            warning: None,
         })
     }
 
-    async fn resolve_image(&mut self) -> Result<(), Error> {
-        if let Some(image) = &mut self.image {
-            // If image WxH was already provided by OpenGraph,
-            // just return that instead.
-            if image.width != 0 && image.height != 0 {
-                return Ok(());
-            }
-
-            let (resp, _) = fetch(&image.url).await?;
-            let (width, height) = consume_size(resp).await?;
+    /// Measure the page's image. Its dominant colour is worked out from
+    /// the same fetch if `colour` is set, as some embeds are themed by it.
+    async fn resolve_image(image: Image, colour: bool) -> Result<(Image, Option<String>), Error> {
+        // Pages sometimes inline their preview, small ones can
+        // be measured without fetching anything.
+        if image.url.starts_with("data:") {
+            let bytes = decode_data_uri(&image.url, *MAX_DATA_URI_SIZE)?;
+            let probe = image_size(&bytes)?;
+            let colour = if colour {
+                dominant_colour(&bytes)
+            } else {
+                None
+            };
+            return Ok((media::probed(image, probe), colour));
+        }
 
-            image.width = width;
-            image.height = height;
+        // If image WxH was already provided by OpenGraph, just return that
+        // instead. Only JPEGs though, anything else may be animated and
+        // that can't be known without looking.
+        let known = image.width != 0 && image.height != 0;
+        if known && is_jpeg(&image.url) && !colour {
+            return Ok((image, None));
         }
 
-        Ok(())
+        let probe = match fetch_image(&image.url).await {
+            Ok((resp, _)) if colour => consume_colour(resp).await,
+            Ok((resp, _)) => consume_size(resp).await.map(|probe| (probe, None)),
+            Err(error) => Err(error),
+        };
+
+        match probe {
+            Ok((probe, colour)) => Ok((media::probed(image, probe), colour)),
+            Err(_) if known => Ok((image, None)),
+            Err(error) => Err(error),
+        }
     }
 
-    pub async fn generate_special(&mut self) -> Result<Special, Error> {
-        lazy_static! {
-            // ! FIXME: use youtube-dl to fetch metadata
-            static ref RE_YOUTUBE: Regex = Regex::new("^(?:(?:https?:)?//)?(?:(?:www|m)\\.)?(?:(?:youtube\\.com|youtu.be))(?:/(?:[\\w\\-]+\\?v=|embed/|v/)?)([\\w\\-]+)(?:\\S+)?$").unwrap();
+    pub async fn generate_special(&self) -> Result<Special, Error> {
+        for provider in IFRAME_PROVIDERS.iter() {
+            if let Some(captures) = provider.pattern.captures(&self.url) {
+                let mut url = String::new();
+                captures.expand(&provider.url, &mut url);
 
-            // ! FIXME: use Twitch API to fetch metadata
-            static ref RE_TWITCH: Regex = Regex::new("^(?:https?://)?(?:www\\.|go\\.)?twitch\\.tv/([a-z0-9_]+)($|\\?)").unwrap();
-            static ref RE_TWITCH_VOD: Regex = Regex::new("^(?:https?://)?(?:www\\.|go\\.)?twitch\\.tv/videos/([0-9]+)($|\\?)").unwrap();
-            static ref RE_TWITCH_CLIP: Regex = Regex::new("^(?:https?://)?(?:www\\.|go\\.)?twitch\\.tv/(?:[a-z0-9_]+)/clip/([A-z0-9_-]+)($|\\?)").unwrap();
+                return Ok(Special::Iframe {
+                    url,
+                    width: provider.width,
+                    height: provider.height,
+                    sandbox: provider.sandbox.clone(),
+                    allow: provider.allow.clone(),
+                    aspect_ratio: aspect_ratio(provider.width, provider.height),
+                });
+            }
+        }
 
-            static ref RE_SPOTIFY: Regex = Regex::new("^(?:https?://)?open.spotify.com/(track|user|artist|album|playlist)/([A-z0-9]+)").unwrap();
-            static ref RE_SOUNDCLOUD: Regex = Regex::new("^(?:https?://)?soundcloud.com/([a-zA-Z0-9-]+)/([A-z0-9-]+)").unwrap();
-            static ref RE_BANDCAMP: Regex = Regex::new("^(?:https?://)?(?:[A-z0-9_-]+).bandcamp.com/(track|album)/([A-z0-9_-]+)").unwrap();
+        if let Some(special) =
+            mastodon::lookup(&self.original_url, self.activity_url.as_deref()).await
+        {
+            return Ok(special);
         }
 
-        if let Some(captures) = RE_YOUTUBE.captures_iter(&self.url).next() {
-            lazy_static! {
-                static ref RE_TIMESTAMP: Regex = Regex::new("(?:\\?|&)(?:t|start)=([\\w]+)").unwrap();
+        if let Some(activity_url) = &self.activity_url {
+            if let Some(special) = activitypub::lookup(activity_url).await {
+                return Ok(special);
             }
+        }
 
+        if let Some(captures) = RE_YOUTUBE_ATTRIBUTION
+            .captures_iter(&self.url)
+            .next()
+            .or_else(|| RE_YOUTUBE.captures_iter(&self.url).next())
+        {
             if let Some(video) = &self.video {
-                if let Some(timestamp_captures) = RE_TIMESTAMP.captures_iter(&video.url).next() {
-                    return Ok(Special::YouTube {
-                        id: captures[1].to_string(),
-                        timestamp: Some(timestamp_captures[1].to_string()),
-                    });
-                }
+                // Prefer the timestamp from the link that was shared, since
+                // og:url and og:video usually have it stripped.
+                let timestamp = RE_TIMESTAMP
+                    .captures_iter(&self.original_url)
+                    .chain(RE_TIMESTAMP.captures_iter(&video.url))
+                    .find_map(|captures| parse_timestamp(&captures[1]));
 
                 return Ok(Special::YouTube {
                     id: captures[1].to_string(),
-                    timestamp: None,
+                    timestamp,
                 });
             }
+        } else if let Some(captures) = RE_VIMEO
+            .captures(&self.original_url)
+            .or_else(|| RE_VIMEO_PLAYER.captures(&self.original_url))
+        {
+            return Ok(Special::Vimeo {
+                id: captures[1].to_string(),
+                hash: captures.get(2).map(|m| m.as_str().to_string()),
+            });
+        } else if let Some(captures) = RE_DAILYMOTION.captures(&self.original_url) {
+            return Ok(Special::Dailymotion {
+                id: captures[1].to_string(),
+            });
         } else if let Some(captures) = RE_TWITCH.captures_iter(&self.url).next() {
-                return Ok(Special::Twitch {
-                    id: captures[1].to_string(),
-                    content_type: TwitchType::Channel,
-                });
+            // Twitch only embeds a BroadcastEvent while the channel is live.
+            let broadcast = self.find_json_ld(|value| value["isLiveBroadcast"] == true);
+            let thumbnail = broadcast.map(|value| {
+                match &value["thumbnailUrl"] {
+                    Value::Array(urls) => urls.last(),
+                    url => Some(url),
+                }
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| {
+                    format!(
+                        "https://static-cdn.jtvnw.net/previews-ttv/live_user_{}-1280x720.jpg",
+                        &captures[1]
+                    )
+                })
+            });
+
+            return Ok(Special::Twitch {
+                id: captures[1].to_string(),
+                content_type: TwitchType::Channel,
+                live: Some(broadcast.is_some()),
+                thumbnail,
+            });
         } else if let Some(captures) = RE_TWITCH_VOD.captures_iter(&self.url).next() {
             return Ok(Special::Twitch {
                 id: captures[1].to_string(),
                 content_type: TwitchType::Video,
+                live: None,
+                thumbnail: None,
             });
         } else if let Some(captures) = RE_TWITCH_CLIP.captures_iter(&self.url).next() {
             return Ok(Special::Twitch {
                 id: captures[1].to_string(),
                 content_type: TwitchType::Clip,
+                live: None,
+                thumbnail: None,
             });
+        } else if let Some(captures) = RE_TWITCH_CATEGORY.captures_iter(&self.url).next() {
+            // Older links use the display name rather than the slug.
+            let slug = captures[1]
+                .replace("%20", "-")
+                .replace(' ', "-")
+                .to_lowercase();
+            return twitch::category(&slug).await.ok_or(Error::ProviderFailed);
         } else if let Some(captures) = RE_SPOTIFY.captures_iter(&self.url).next() {
             return Ok(Special::Spotify {
                 content_type: captures[1].to_string(),
                 id: captures[2].to_string(),
+                artwork: self.image.as_ref().map(|image| image.url.clone()),
+                // Filled in from the artwork once the image step has it.
+                colour: None,
+                links: self.resolve_songlink().await,
+            });
+        } else if let Some(captures) = RE_DEEZER.captures(&self.url) {
+            // Short links land on the page, which names itself in og:url.
+            return Ok(Special::Deezer {
+                content_type: captures[1].to_string(),
+                id: captures[2].to_string(),
+            });
+        } else if let Some(captures) = self
+            .final_url
+            .as_deref()
+            .filter(|_| RE_DEEZER_SHORT.is_match(&self.original_url))
+            .and_then(|url| RE_DEEZER.captures(url))
+        {
+            // Otherwise the redirect it took says where it went.
+            return Ok(Special::Deezer {
+                content_type: captures[1].to_string(),
+                id: captures[2].to_string(),
+            });
+        } else if RE_SONGLINK.is_match(&self.url) {
+            return songlink::lookup(&self.url)
+                .await
+                .map(|links| Special::Songlink { links })
+                .ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_TIDAL.captures(&self.original_url) {
+            return Ok(Special::Tidal {
+                content_type: captures[1].to_string(),
+                id: captures[2].to_string(),
+            });
+        } else if let Some(captures) = RE_APPLE_MUSIC.captures(&self.original_url) {
+            // Songs shared from an album point at the album, with the song in `i`.
+            let song = Url::parse(&self.original_url).ok().and_then(|url| {
+                url.query_pairs()
+                    .find(|(key, _)| key == "i")
+                    .map(|(_, id)| id.into_owned())
+            });
+
+            let (content_type, id) = match (&captures[2], song) {
+                ("album", Some(song)) => (AppleMusicType::Song, song),
+                ("album", None) => (AppleMusicType::Album, captures[3].to_string()),
+                ("song", _) => (AppleMusicType::Song, captures[3].to_string()),
+                _ => (AppleMusicType::Playlist, captures[3].to_string()),
+            };
+
+            return Ok(Special::AppleMusic {
+                storefront: captures[1].to_string(),
+                content_type,
+                id,
+            });
+        } else if let Some(captures) = RE_MIXCLOUD
+            .captures(&self.original_url)
+            .filter(|captures| is_mixcloud_show(&captures[1], &captures[2]))
+        {
+            return Ok(Special::Mixcloud {
+                user: captures[1].to_string(),
+                slug: captures[2].to_string(),
             });
         } else if RE_SOUNDCLOUD.is_match(&self.url) {
-            return Ok(Special::Soundcloud);
+            return Ok(Special::Soundcloud {
+                secret_token: RE_SECRET_TOKEN
+                    .captures_iter(&self.original_url)
+                    .chain(RE_SECRET_TOKEN.captures_iter(&self.url))
+                    .next()
+                    .map(|captures| captures[1].to_string()),
+                links: self.resolve_songlink().await,
+            });
         } else if RE_BANDCAMP.is_match(&self.url) {
-            lazy_static! {
-                static ref RE_TRACK: Regex = Regex::new("track=(\\d+)").unwrap();
-                static ref RE_ALBUM: Regex = Regex::new("album=(\\d+)").unwrap();
-            }
-
             if let Some(video) = &self.video {
                 if let Some(captures) = RE_TRACK.captures_iter(&video.url).next() {
-                    return Ok(Special::Bandcamp { content_type: BandcampType::Track, id: captures[1].to_string() })
+                    return Ok(Special::Bandcamp {
+                        content_type: BandcampType::Track,
+                        id: captures[1].to_string(),
+                    });
                 }
 
                 if let Some(captures) = RE_ALBUM.captures_iter(&video.url).next() {
-                    return Ok(Special::Bandcamp { content_type: BandcampType::Album, id: captures[1].to_string() })
+                    return Ok(Special::Bandcamp {
+                        content_type: BandcampType::Album,
+                        id: captures[1].to_string(),
+                    });
                 }
             }
+        } else if let Some(captures) = RE_MATRIX.captures_iter(&self.original_url).next() {
+            return matrix::lookup(&captures[1], captures.get(2).map(|m| m.as_str()))
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_DISCORD_INVITE.captures_iter(&self.url).next() {
+            return discord::lookup(&captures[1])
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_INSTAGRAM.captures_iter(&self.url).next() {
+            if let Some(oembed) = instagram::oembed(&self.url).await {
+                return Ok(Special::Instagram {
+                    id: captures[1].to_string(),
+                    caption: oembed.title,
+                    author: oembed.author_name,
+                    image: oembed.thumbnail_url,
+                });
+            }
+
+            // Without a token, fall back to whatever the page preloads.
+            let post = self.find_json_ld(|value| value["articleBody"].is_string());
+            return Ok(Special::Instagram {
+                id: captures[1].to_string(),
+                caption: post
+                    .and_then(|post| post["articleBody"].as_str())
+                    .map(str::to_string)
+                    .or_else(|| self.description.clone()),
+                author: post
+                    .and_then(|post| post["author"]["alternateName"].as_str())
+                    .map(|author| author.trim_start_matches('@').to_string()),
+                image: post
+                    .and_then(|post| match &post["image"] {
+                        Value::Array(images) => images.first(),
+                        image => Some(image),
+                    })
+                    .and_then(|image| image["url"].as_str().or_else(|| image.as_str()))
+                    .map(str::to_string)
+                    .or_else(|| self.image.as_ref().map(|image| image.url.clone())),
+            });
+        } else if twitter::is_status(&self.original_url) {
+            return twitter::lookup(&self.original_url, self.description.clone())
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if bluesky::is_post(&self.original_url) {
+            return bluesky::lookup(&self.original_url)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if gitlab::is_gitlab(&self.original_url, self.generator.as_deref()) {
+            return gitlab::lookup(&self.original_url)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if steam::is_app(&self.original_url) {
+            return steam::lookup(&self.original_url)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if wikipedia::is_article(&self.original_url) {
+            return wikipedia::lookup(&self.original_url)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if imgur::is_album(&self.original_url) {
+            return imgur::lookup(&self.original_url)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if reddit::is_reddit(&self.url) {
+            return reddit::lookup(&self.url).await.ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_TIKTOK
+            .captures(&self.url)
+            .or_else(|| RE_TIKTOK.captures(&self.original_url))
+        {
+            let id = captures[1].to_string();
+            let oembed = tiktok::oembed(&captures[0]).await;
+            let (caption, author, thumbnail) = match oembed {
+                Some(oembed) => (oembed.title, oembed.author_name, oembed.thumbnail_url),
+                None => (None, None, None),
+            };
+
+            return Ok(Special::TikTok {
+                id,
+                caption: caption.or_else(|| self.description.clone()),
+                author,
+                thumbnail: thumbnail.or_else(|| self.image.as_ref().map(|image| image.url.clone())),
+            });
+        } else if let Some(captures) = RE_TUMBLR
+            .captures(&self.original_url)
+            .filter(|captures| &captures[1] != "www")
+            .or_else(|| RE_TUMBLR_DASHBOARD.captures(&self.original_url))
+        {
+            return Ok(Special::Tumblr {
+                blog: captures[1].to_string(),
+                id: captures[2].to_string(),
+                summary: self.description.clone(),
+                image: self.image.as_ref().map(|image| image.url.clone()),
+            });
+        } else if let Some(special) =
+            facebook::special(&self.original_url).or_else(|| facebook::special(&self.url))
+        {
+            return Ok(special);
+        } else if RE_DEVIANTART.is_match(&self.url) {
+            return artwork::deviantart(&self.url)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_ARTSTATION.captures_iter(&self.url).next() {
+            return artwork::artstation(&captures[1])
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if RE_FLICKR.is_match(&self.url) {
+            return photo::flickr(&self.url).await.ok_or(Error::ProviderFailed);
+        } else if RE_UNSPLASH.is_match(&self.url) {
+            if let Some(special) = photo::unsplash(
+                self.title.as_deref(),
+                self.image.as_ref().map(|image| image.url.as_str()),
+            ) {
+                return Ok(special);
+            }
+        } else if RE_SPEAKERDECK.is_match(&self.url) {
+            return slides::speakerdeck(&self.url)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if RE_SLIDESHARE.is_match(&self.url) {
+            return slides::slideshare(&self.url)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_ARCHIVE.captures_iter(&self.url).next() {
+            return archive::item(&captures[1])
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_DROPBOX.captures_iter(&self.original_url).next() {
+            return drive::dropbox(&self.original_url, &captures[1])
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_GOOGLE_DRIVE.captures_iter(&self.original_url).next() {
+            return drive::google_drive(&captures[1], self.title.as_deref())
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if RE_ONEDRIVE.is_match(&self.original_url) {
+            return drive::onedrive(&self.original_url)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_PASTEBIN.captures_iter(&self.original_url).next() {
+            let raw = format!("https://pastebin.com/raw/{}", &captures[1]);
+            return paste::raw(PasteProvider::Pastebin, &raw, None)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_GIST.captures_iter(&self.original_url).next() {
+            if let Some(special) = paste::gist(&captures[2]).await {
+                return Ok(special);
+            }
+
+            let owner = captures.get(1).map(|m| m.as_str().to_string());
+            return Ok(github::gist(owner, &captures[2]));
+        } else if github::is_repository(&self.original_url) {
+            return github::lookup(&self.original_url)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_HASTEBIN.captures_iter(&self.original_url).next() {
+            // Hastebin only knows the language from the extension in the link.
+            let raw = format!("https://{}/raw/{}", &captures[1], &captures[2]);
+            let language = captures.get(3).map(|m| m.as_str().to_string());
+            return paste::raw(PasteProvider::Hastebin, &raw, language)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if RE_ITCH.is_match(&self.url) {
+            let cover = self.image.as_ref().map(|image| image.url.clone());
+            return itch::game(&self.url, cover)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_KICKSTARTER.captures_iter(&self.url).next() {
+            // The stats endpoint has no goal, so use the page's JSON-LD if it has one.
+            let goal = self
+                .find_json_ld(|value| value["offers"]["price"].is_number())
+                .and_then(|value| value["offers"]["price"].as_f64());
+
+            return campaign::kickstarter(&captures[1], goal)
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_PATREON.captures_iter(&self.url).next() {
+            return campaign::patreon(&captures[1])
+                .await
+                .ok_or(Error::ProviderFailed);
+        } else if let Some(captures) = RE_YAHOO_FINANCE.captures_iter(&self.url).next() {
+            if *STOCK_EMBEDS {
+                return data::stock(&captures[1]).await.ok_or(Error::ProviderFailed);
+            }
+        } else if let Some(captures) = RE_OPENWEATHERMAP.captures_iter(&self.url).next() {
+            // Without a key the page is all there is to go on.
+            if OPENWEATHERMAP_KEY.is_some() {
+                return data::weather(&captures[1])
+                    .await
+                    .ok_or(Error::ProviderFailed);
+            }
+        } else if let Some(captures) = RE_GIPHY.captures_iter(&self.url).next() {
+            let id = &captures[1];
+            let gif = format!("https://media.giphy.com/media/{}/giphy.gif", id);
+            let (width, height) = match gif_size(&gif).await {
+                Some(size) => size,
+                None => self.media_size(),
+            };
+
+            return Ok(Special::Gif {
+                provider: GifProvider::Giphy,
+                id: id.to_string(),
+                width,
+                height,
+                preview: Some(format!("https://media.giphy.com/media/{}/200w.gif", id)),
+                gif: Some(gif),
+                mp4: Some(format!("https://media.giphy.com/media/{}/giphy.mp4", id)),
+            });
+        } else if let Some(captures) = RE_TENOR.captures_iter(&self.url).next() {
+            let gif = self.image.as_ref().map(|image| image.url.clone());
+            let size = match &gif {
+                Some(gif) => gif_size(gif).await,
+                None => None,
+            };
+            let (width, height) = size.unwrap_or_else(|| self.media_size());
+
+            return Ok(Special::Gif {
+                provider: GifProvider::Tenor,
+                id: captures[1].to_string(),
+                width,
+                height,
+                // Tenor renditions differ only by the suffix on the media id,
+                // AAAC being the full GIF and AAAM the tiny preview.
+                preview: gif
+                    .as_ref()
+                    .filter(|url| url.contains("AAAC/"))
+                    .map(|url| url.replacen("AAAC/", "AAAM/", 1)),
+                gif,
+                mp4: self.video.as_ref().map(|video| video.url.clone()),
+            });
         }
 
         Ok(Special::None)
     }
 
+    /// Point the image at the GIF itself, as the page's preview is often
+    /// a still or a rendition sized differently to the one played.
+    fn use_gif(&mut self) {
+        if let Some(Special::Gif {
+            gif: Some(gif),
+            width,
+            height,
+            ..
+        }) = &self.special
+        {
+            self.image = Some(Image {
+                url: gif.clone(),
+                width: *width,
+                height: *height,
+                size: ImageSize::Large,
+                animated: true,
+                size_bytes: None,
+                format: Some("gif".to_string()),
+            });
+        }
+    }
+
+    /// Dimensions of the page's media, preferring the video.
+    fn media_size(&self) -> (isize, isize) {
+        if let Some(video) = self
+            .video
+            .as_ref()
+            .filter(|v| v.width != 0 && v.height != 0)
+        {
+            (video.width, video.height)
+        } else if let Some(image) = &self.image {
+            (image.width, image.height)
+        } else {
+            (0, 0)
+        }
+    }
+
+    /// Find the first JSON-LD object in the document matching the predicate,
+    /// looking inside `@graph` containers as well.
+    fn find_json_ld<F: Fn(&Value) -> bool>(&self, predicate: F) -> Option<&Value> {
+        json_ld_objects(&self.json_ld).find(|value| predicate(value))
+    }
+
+    /// Look up links to other music providers, if enabled.
+    async fn resolve_songlink(&self) -> Option<BTreeMap<String, String>> {
+        if !*SONGLINK_RESOLVE {
+            return None;
+        }
+
+        songlink::lookup(&self.url).await
+    }
+
+    /// Fetch the web app manifest, if the page links one.
+    async fn resolve_manifest(&self) -> Option<Result<manifest::Manifest, Error>> {
+        Some(manifest::lookup(self.manifest_url.as_ref()?).await)
+    }
+
+    fn warn(&mut self, field: &'static str, error: Error) {
+        self.warnings.push(Warning {
+            field: field.to_string(),
+            error: serde_json::to_value(&error).unwrap_or_default(),
+        });
+    }
+
     pub async fn resolve_external(&mut self) {
-        if let Ok(special) = self.generate_special().await {
-            self.special = Some(special);
+        // Icon and colour are derived while parsing, so only the
+        // steps which hit the network need to be run here.
+        let image = self.image.clone();
+        if let Some(image) = &image {
+            referer::remember(&image.url, &self.original_url);
+        }
+
+        // Spotify embeds are themed by the colour of their artwork.
+        let colour = RE_SPOTIFY.is_match(&self.url);
+        let ((special, clip), image, manifest) = join!(
+            async {
+                let special = self.generate_special().await;
+
+                // Clips can be played inline if we can find the direct rendition.
+                let clip = match &special {
+                    Ok(Special::Twitch {
+                        content_type: TwitchType::Clip,
+                        id,
+                        ..
+                    }) => twitch::clip_video(id).await,
+                    _ => None,
+                };
+
+                (special, clip)
+            },
+            async {
+                match image {
+                    Some(image) => Some(Metadata::resolve_image(image, colour).await),
+                    None => None,
+                }
+            },
+            self.resolve_manifest()
+        );
+
+        metrics::record(special.as_ref());
+
+        match manifest {
+            Some(Ok(manifest)) => {
+                // Manifest icons and colours are picked for installing the app,
+                // so they tend to be of better quality than the meta tags.
+                self.icon_url = manifest.icon_url().or_else(|| self.icon_url.take());
+                self.colour = manifest
+                    .theme_color
+                    .as_deref()
+                    .and_then(colour::normalise)
+                    .or_else(|| self.colour.take())
+                    .or_else(|| {
+                        manifest
+                            .background_color
+                            .as_deref()
+                            .and_then(colour::normalise)
+                    });
+                self.site_name = self
+                    .site_name
+                    .take()
+                    .or(manifest.name)
+                    .or(manifest.short_name);
+            }
+            Some(Err(error)) => self.warn("manifest", error),
+            None => {}
         }
 
-        if self.resolve_image().await.is_err() {
-            self.image = None;
+        let mut artwork_colour = None;
+        self.image = match image {
+            Some(Ok((image, colour))) => {
+                artwork_colour = colour;
+                Some(image)
+            }
+            Some(Err(error)) => {
+                self.warn("image", error);
+                None
+            }
+            None => None,
+        };
+
+        match special {
+            Ok(mut special) => {
+                if let Some(video) = clip {
+                    self.video = Some(video);
+                }
+
+                if let Special::Spotify { colour, .. } = &mut special {
+                    *colour = artwork_colour;
+                }
+
+                self.special = Some(special);
+            }
+            Err(error) => self.warn("special", error),
         }
+
+        self.use_gif();
+
+        // Fall back to a frame of the video when the page has no image.
+        if let (None, Some(video)) = (&self.image, &self.video) {
+            if let Some(url) = ffmpeg::poster_url(&video.url) {
+                self.image = Some(Image {
+                    url,
+                    width: video.width,
+                    height: video.height,
+                    size: ImageSize::Large,
+                    animated: false,
+                    size_bytes: None,
+                    format: None,
+                });
+            }
+        }
+    }
+
+    pub fn set_archived(&mut self, snapshot: Snapshot) {
+        self.archived = Some(snapshot);
+    }
+
+    pub fn has_warnings(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+
+    pub fn is_dangerous(&self) -> bool {
+        self.dangerous
+    }
+
+    pub fn is_opted_out(&self) -> bool {
+        self.opted_out
+    }
+
+    /// Drop everything scraped from the page, keeping only the link.
+    pub fn url_only(self) -> Metadata {
+        Metadata::from_url(self.original_url)
     }
 
     pub fn is_none(&self) -> bool {
         self.title.is_none() && self.description.is_none() && self.image.is_none()
     }
 }
+
+/// Whether an image URL names a JPEG, going by its extension.
+fn is_jpeg(url: &str) -> bool {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            let name = url.path_segments()?.next_back()?.to_lowercase();
+            Some(name.ends_with(".jpg") || name.ends_with(".jpeg"))
+        })
+        .unwrap_or(false)
+}
+
+/// Mixcloud profile tabs and site pages share the shape of show links.
+fn is_mixcloud_show(user: &str, slug: &str) -> bool {
+    const PAGES: &[&str] = &[
+        "discover",
+        "categories",
+        "tag",
+        "upload",
+        "select",
+        "live",
+        "settings",
+        "dashboard",
+    ];
+    const TABS: &[&str] = &[
+        "uploads",
+        "favorites",
+        "listens",
+        "stream",
+        "playlists",
+        "followers",
+        "following",
+        "reposts",
+        "shows",
+        "stats",
+    ];
+
+    !PAGES.contains(&user) && !TABS.contains(&slug)
+}
+
+fn aspect_ratio(width: isize, height: isize) -> Option<f64> {
+    if width > 0 && height > 0 {
+        Some(width as f64 / height as f64)
+    } else {
+        None
+    }
+}
+
+/// Normalise a YouTube timestamp such as `90`, `90s` or `1h2m3s` to seconds.
+fn parse_timestamp(timestamp: &str) -> Option<String> {
+    let captures = RE_DURATION.captures(timestamp)?;
+    let mut seconds = 0;
+    for (index, multiplier) in [(1, 3600), (2, 60), (3, 1)].iter() {
+        if let Some(value) = captures.get(*index) {
+            seconds += value.as_str().parse::<u64>().ok()? * multiplier;
+        }
+    }
+
+    if seconds == 0 {
+        return None;
+    }
+
+    Some(seconds.to_string())
+}
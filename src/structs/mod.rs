@@ -1,4 +1,6 @@
+pub mod calendar;
 pub mod embed;
 pub mod media;
 pub mod metadata;
+pub mod route;
 pub mod special;
@@ -0,0 +1,156 @@
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::util::variables::STATIC_MAP_URL;
+
+lazy_static! {
+    static ref RE_SEGMENT: Regex = Regex::new("(?s)<(trkseg|rte)\\b.*?</(?:trkseg|rte)>").unwrap();
+    static ref RE_POINT: Regex = Regex::new("<(?:trkpt|rtept|wpt)\\b[^>]*>").unwrap();
+    static ref RE_LAT: Regex = Regex::new("\\blat=[\"']([-0-9.]+)[\"']").unwrap();
+    static ref RE_LON: Regex = Regex::new("\\blon=[\"']([-0-9.]+)[\"']").unwrap();
+}
+
+/// Compile patterns ahead of the first request, returning how many.
+pub fn warm() -> usize {
+    let patterns: &[&Regex] = &[&RE_SEGMENT, &RE_POINT, &RE_LAT, &RE_LON];
+    patterns.len()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Route {
+    // [min_lon, min_lat, max_lon, max_lat]
+    bbox: [f64; 4],
+    // Metres along all lines in the file.
+    distance: f64,
+    points: usize,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thumbnail: Option<String>,
+}
+
+/// Great-circle distance between two (lon, lat) points in metres.
+fn haversine((lon1, lat1): (f64, f64), (lon2, lat2): (f64, f64)) -> f64 {
+    let (dlat, dlon) = ((lat2 - lat1).to_radians(), (lon2 - lon1).to_radians());
+    let a = (dlat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * 6_371_000.0 * a.sqrt().asin()
+}
+
+impl Route {
+    fn from_lines(lines: Vec<Vec<(f64, f64)>>) -> Option<Route> {
+        let points: Vec<&(f64, f64)> = lines.iter().flatten().collect();
+        if points.is_empty() {
+            return None;
+        }
+
+        let mut bbox = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+        for (lon, lat) in &points {
+            bbox = [
+                bbox[0].min(*lon),
+                bbox[1].min(*lat),
+                bbox[2].max(*lon),
+                bbox[3].max(*lat),
+            ];
+        }
+
+        let distance = lines
+            .iter()
+            .flat_map(|line| line.windows(2))
+            .map(|pair| haversine(pair[0], pair[1]))
+            .sum();
+
+        Some(Route {
+            bbox,
+            distance,
+            points: points.len(),
+            thumbnail: STATIC_MAP_URL.as_ref().map(|template| {
+                template.replace(
+                    "{bbox}",
+                    &format!("{},{},{},{}", bbox[0], bbox[1], bbox[2], bbox[3]),
+                )
+            }),
+        })
+    }
+
+    pub fn from_geojson(document: &str) -> Option<Route> {
+        fn position(value: &Value) -> Option<(f64, f64)> {
+            Some((value.get(0)?.as_f64()?, value.get(1)?.as_f64()?))
+        }
+
+        fn positions(value: &Value) -> Vec<(f64, f64)> {
+            value
+                .as_array()
+                .map(|values| values.iter().filter_map(position).collect())
+                .unwrap_or_default()
+        }
+
+        // Points are collected as lines of their own so they count towards
+        // the bounding box without adding to the distance.
+        fn collect(geometry: &Value, lines: &mut Vec<Vec<(f64, f64)>>) {
+            let coordinates = &geometry["coordinates"];
+            match geometry["type"].as_str() {
+                Some("Feature") => collect(&geometry["geometry"], lines),
+                Some("FeatureCollection") => geometry["features"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .for_each(|feature| collect(feature, lines)),
+                Some("GeometryCollection") => geometry["geometries"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .for_each(|geometry| collect(geometry, lines)),
+                Some("Point") => lines.extend(position(coordinates).map(|p| vec![p])),
+                Some("MultiPoint") => {
+                    lines.extend(positions(coordinates).into_iter().map(|p| vec![p]))
+                }
+                Some("LineString") => lines.push(positions(coordinates)),
+                Some("MultiLineString") | Some("Polygon") => coordinates
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .for_each(|line| lines.push(positions(line))),
+                Some("MultiPolygon") => coordinates
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_array)
+                    .flatten()
+                    .for_each(|line| lines.push(positions(line))),
+                _ => {}
+            }
+        }
+
+        let document: Value = serde_json::from_str(document).ok()?;
+        let mut lines = Vec::new();
+        collect(&document, &mut lines);
+        Route::from_lines(lines)
+    }
+
+    pub fn from_gpx(document: &str) -> Option<Route> {
+        let points = |text: &str| -> Vec<(f64, f64)> {
+            RE_POINT
+                .find_iter(text)
+                .filter_map(|tag| {
+                    let tag = tag.as_str();
+                    Some((
+                        RE_LON.captures(tag)?[1].parse().ok()?,
+                        RE_LAT.captures(tag)?[1].parse().ok()?,
+                    ))
+                })
+                .collect()
+        };
+
+        let mut lines: Vec<Vec<(f64, f64)>> = RE_SEGMENT
+            .find_iter(document)
+            .map(|segment| points(segment.as_str()))
+            .collect();
+
+        // Waypoints outside of any track or route only extend the bounding box.
+        let remainder = RE_SEGMENT.replace_all(document, "");
+        lines.extend(points(&remainder).into_iter().map(|p| vec![p]));
+
+        Route::from_lines(lines)
+    }
+}
@@ -0,0 +1,92 @@
+use actix_web::HttpRequest;
+use lru::LruCache;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::quota;
+use super::result::Error;
+use super::variables::{DAILY_BYTE_QUOTA, QUOTA_CLIENTS};
+
+/// Bytes proxied today, by origin host and by client.
+#[derive(Serialize)]
+pub struct Usage {
+    hosts: HashMap<String, u64>,
+    clients: HashMap<String, u64>,
+}
+
+/// Today's counts, keeping the most recently seen hosts and clients
+/// once there are more than `QUOTA_CLIENTS` of either.
+struct Tally {
+    day: u64,
+    hosts: LruCache<String, u64>,
+    clients: LruCache<String, u64>,
+}
+
+lazy_static! {
+    static ref USAGE: Mutex<Tally> = Mutex::new(Tally {
+        day: 0,
+        hosts: LruCache::new(*QUOTA_CLIENTS),
+        clients: LruCache::new(*QUOTA_CLIENTS),
+    });
+}
+
+fn with_today<T>(f: impl FnOnce(&mut Tally) -> T) -> T {
+    let mut usage = USAGE.lock().unwrap();
+    let day = quota::today().map(|(_, day)| day).unwrap_or(0);
+    if usage.day != day {
+        usage.day = day;
+        usage.hosts.clear();
+        usage.clients.clear();
+    }
+
+    f(&mut usage)
+}
+
+fn add(counts: &mut LruCache<String, u64>, key: String, bytes: usize) {
+    let total = counts.get(&key).copied().unwrap_or(0) + bytes as u64;
+    counts.put(key, total);
+}
+
+/// Refuse clients which have used up their daily byte quota.
+pub fn check(req: &HttpRequest) -> Result<(), Error> {
+    let (limit, client) = match (
+        *DAILY_BYTE_QUOTA,
+        quota::client(req.headers(), req.peer_addr(), &req.connection_info()),
+    ) {
+        (Some(limit), Some(client)) => (limit, client),
+        _ => return Ok(()),
+    };
+
+    let used = with_today(|usage| usage.clients.peek(&client).copied().unwrap_or(0));
+    if used >= limit {
+        return Err(Error::QuotaExceeded);
+    }
+
+    Ok(())
+}
+
+/// Account for a proxied response.
+pub fn record(req: &HttpRequest, url: &str, bytes: usize) {
+    let host = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase));
+    let client = quota::client(req.headers(), req.peer_addr(), &req.connection_info());
+
+    with_today(|usage| {
+        if let Some(host) = host {
+            add(&mut usage.hosts, host, bytes);
+        }
+
+        if let Some(client) = client {
+            add(&mut usage.clients, client, bytes);
+        }
+    });
+}
+
+pub fn snapshot() -> Usage {
+    with_today(|usage| Usage {
+        hosts: usage.hosts.iter().map(|(k, v)| (k.clone(), *v)).collect(),
+        clients: usage.clients.iter().map(|(k, v)| (k.clone(), *v)).collect(),
+    })
+}
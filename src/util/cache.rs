@@ -0,0 +1,131 @@
+use lru::LruCache;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct Entries<K: Hash + Eq, V> {
+    lru: LruCache<K, (Instant, V)>,
+    weight: usize,
+}
+
+/// Bounded LRU cache whose entries expire after a fixed time, unless
+/// they were inserted with one of their own.
+///
+/// Besides the number of entries, the cache can be bounded by their
+/// total weight, such as the bytes of the bodies it keeps.
+pub struct Cache<K: Hash + Eq, V: Clone> {
+    entries: Mutex<Entries<K, V>>,
+    ttl: Duration,
+    budget: usize,
+    weigh: fn(&V) -> usize,
+}
+
+impl<K: Hash + Eq, V: Clone> Cache<K, V> {
+    pub fn new(capacity: usize, ttl: Duration) -> Cache<K, V> {
+        Cache::with_budget(capacity, usize::MAX, ttl, |_| 0)
+    }
+
+    /// Also evict the least recently used entries while the weight of
+    /// everything kept is over `budget`.
+    pub fn with_budget(
+        capacity: usize,
+        budget: usize,
+        ttl: Duration,
+        weigh: fn(&V) -> usize,
+    ) -> Cache<K, V> {
+        Cache {
+            entries: Mutex::new(Entries {
+                lru: LruCache::new(capacity),
+                weight: 0,
+            }),
+            ttl,
+            budget,
+            weigh,
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some((expires, value)) = entries.lru.get(key) {
+            if Instant::now() < *expires {
+                return Some(value.clone());
+            }
+
+            if let Some((_, value)) = entries.lru.pop(key) {
+                entries.weight -= (self.weigh)(&value);
+            }
+        }
+
+        None
+    }
+
+    /// Like `get`, but expired entries are kept and handed back too,
+    /// along with whether the entry is still fresh, so they can be
+    /// revalidated rather than fetched again.
+    pub fn get_stale(&self, key: &K) -> Option<(V, bool)> {
+        let mut entries = self.entries.lock().unwrap();
+        entries
+            .lru
+            .get(key)
+            .map(|(expires, value)| (value.clone(), Instant::now() < *expires))
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        self.insert_for(key, value, self.ttl);
+    }
+
+    pub fn insert_for(&self, key: K, value: V, ttl: Duration) {
+        let weight = (self.weigh)(&value);
+        if weight > self.budget {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if let Some((_, previous)) = entries.lru.pop(&key) {
+            entries.weight -= (self.weigh)(&previous);
+        }
+
+        // Make room first, so that `put` evicts one for the count alone.
+        while entries.weight + weight > self.budget
+            || (entries.lru.len() == entries.lru.cap() && entries.lru.cap() > 0)
+        {
+            match entries.lru.pop_lru() {
+                Some((_, (_, evicted))) => entries.weight -= (self.weigh)(&evicted),
+                None => break,
+            }
+        }
+
+        entries.weight += weight;
+        entries.lru.put(key, (Instant::now() + ttl, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_by_weight() {
+        let cache: Cache<u8, Vec<u8>> =
+            Cache::with_budget(10, 5, Duration::from_secs(60), |value| value.len());
+        cache.insert(1, vec![0; 2]);
+        cache.insert(2, vec![0; 2]);
+        cache.insert(3, vec![0; 2]);
+        assert!(cache.get(&1).is_none());
+        assert!(cache.get(&2).is_some());
+        assert!(cache.get(&3).is_some());
+
+        // Too heavy to keep at all.
+        cache.insert(4, vec![0; 6]);
+        assert!(cache.get(&4).is_none());
+        assert!(cache.get(&3).is_some());
+    }
+
+    #[test]
+    fn keeps_stale_entries_for_revalidation() {
+        let cache: Cache<u8, u8> = Cache::new(10, Duration::from_secs(60));
+        cache.insert_for(1, 1, Duration::from_secs(0));
+        assert_eq!(cache.get_stale(&1), Some((1, false)));
+        assert_eq!(cache.get(&1), None);
+    }
+}
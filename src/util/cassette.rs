@@ -0,0 +1,307 @@
+use log::warn;
+use reqwest::{Client, Request, Response, ResponseBuilderExt, Url};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+
+use super::result::Error;
+use super::variables::{CASSETTE_DIR, CASSETTE_RECORD};
+
+/// A recorded upstream exchange, stored one per file so recordings
+/// can be reviewed, edited and committed alongside what uses them.
+#[derive(Serialize, Deserialize)]
+struct Recording {
+    method: String,
+    url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    // Base64, as bodies are often images.
+    body: String,
+}
+
+/// A directory of recordings, either played back or being made.
+#[derive(Clone)]
+pub struct Cassette {
+    pub dir: PathBuf,
+    pub record: bool,
+}
+
+lazy_static! {
+    static ref CASSETTE: Option<Cassette> = CASSETTE_DIR.clone().map(|dir| Cassette {
+        dir,
+        record: *CASSETTE_RECORD,
+    });
+}
+
+tokio::task_local! {
+    static SCOPED: Cassette;
+}
+
+/// Run requests against `cassette` rather than the configured one,
+/// so tests can replay recordings of their own.
+pub async fn scope<F: Future>(cassette: Cassette, fut: F) -> F::Output {
+    SCOPED.scope(cassette, fut).await
+}
+
+fn current() -> Option<Cassette> {
+    SCOPED
+        .try_with(Cassette::clone)
+        .ok()
+        .or_else(|| CASSETTE.clone())
+}
+
+// Query parameters which carry API keys or signed tokens.
+const SECRET_PARAMETERS: &[&str] = &[
+    "access_token",
+    "api_key",
+    "apikey",
+    "appid",
+    "client_secret",
+    "key",
+    "sig",
+    "token",
+];
+
+// Response headers which hand out sessions.
+const SECRET_HEADERS: &[&str] = &["set-cookie", "set-cookie2"];
+
+/// The URL with credentials taken out of its query, as recordings are
+/// committed. Requests are told apart without them, too, so the same
+/// recording is found whichever key it is replayed with.
+fn scrub(url: &Url) -> Url {
+    let mut scrubbed = url.clone();
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !SECRET_PARAMETERS.contains(&key.as_ref()))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+
+    if pairs.is_empty() {
+        scrubbed.set_query(None);
+    } else {
+        scrubbed.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+
+    scrubbed
+}
+
+/// Where the exchange for one request is kept, when a cassette
+/// directory is configured.
+pub struct Tape {
+    path: PathBuf,
+    method: String,
+    url: String,
+    record: bool,
+}
+
+// FNV-1a, so file names stay the same between builds and machines.
+fn fingerprint(parts: &[&[u8]]) -> u64 {
+    parts
+        .iter()
+        .flat_map(|part| part.iter())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Whether upstream requests are answered from recordings alone.
+pub fn replaying() -> bool {
+    matches!(current(), Some(cassette) if !cassette.record)
+}
+
+/// Find the tape for a request. Requests are told apart by method,
+/// URL and body, so POSTs to the same API don't overwrite each other.
+pub fn tape(request: &Request) -> Option<Tape> {
+    let cassette = current()?;
+    let method = request.method().to_string();
+    let url = scrub(request.url()).to_string();
+    let body = request
+        .body()
+        .and_then(|body| body.as_bytes())
+        .unwrap_or_default();
+
+    let host: String = request
+        .url()
+        .host_str()
+        .unwrap_or("unknown")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let hash = fingerprint(&[method.as_bytes(), b" ", url.as_bytes(), b"\n", body]);
+    let path = cassette.dir.join(format!(
+        "{}-{}-{:016x}.json",
+        method.to_lowercase(),
+        host,
+        hash
+    ));
+
+    Some(Tape {
+        path,
+        method,
+        url,
+        record: cassette.record,
+    })
+}
+
+impl Tape {
+    /// Play back the recorded response. Nothing is sent upstream while
+    /// replaying, so a missing recording fails the request.
+    pub fn replay(&self) -> Option<Result<Response, Error>> {
+        if self.record {
+            return None;
+        }
+
+        let recording = fs::read(&self.path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Recording>(&bytes).ok());
+        Some(match recording {
+            Some(recording) => rebuild(&recording).ok_or(Error::ReqwestFailed),
+            None => {
+                warn!(
+                    "No recording of {} {} at {}.",
+                    self.method,
+                    self.url,
+                    self.path.display()
+                );
+                Err(Error::ReqwestFailed)
+            }
+        })
+    }
+
+    /// Store a live response before handing back an identical copy.
+    pub async fn record(&self, resp: Response) -> Result<Response, Error> {
+        let status = resp.status().as_u16();
+        let headers = resp
+            .headers()
+            .iter()
+            .filter(|(name, _)| !SECRET_HEADERS.contains(&name.as_str()))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = resp.bytes().await.map_err(|_| Error::ReqwestFailed)?;
+
+        let recording = Recording {
+            method: self.method.clone(),
+            url: self.url.clone(),
+            status,
+            headers,
+            body: base64::encode(&body),
+        };
+
+        let written = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&self.path, serde_json::to_vec_pretty(&recording)?));
+        if let Err(error) = written {
+            warn!("Failed to record {} {}: {}", self.method, self.url, error);
+        }
+
+        rebuild(&recording).ok_or(Error::ReqwestFailed)
+    }
+}
+
+fn rebuild(recording: &Recording) -> Option<Response> {
+    // Redirects are resolved against the URL, so it has to come back too.
+    let url = Url::parse(&recording.url).ok()?;
+    let mut response = http::Response::builder().status(recording.status).url(url);
+    for (name, value) in &recording.headers {
+        response = response.header(name.as_str(), value.as_str());
+    }
+
+    let body = base64::decode(&recording.body).ok()?;
+    response.body(body).ok().map(Response::from)
+}
+
+/// Send a request upstream, or play it back from the cassette.
+pub async fn execute(client: &Client, request: Request) -> Result<Response, Error> {
+    let tape = tape(&request);
+    if let Some(replayed) = tape.as_ref().and_then(Tape::replay) {
+        return replayed;
+    }
+
+    let resp = client.execute(request).await.map_err(|err| {
+        if err.is_timeout() {
+            Error::RequestTimedOut
+        } else {
+            Error::ReqwestFailed
+        }
+    })?;
+
+    match tape {
+        Some(tape) => tape.record(resp).await,
+        None => Ok(resp),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::rt::System;
+
+    #[test]
+    fn replays_what_was_recorded() {
+        let dir = std::env::temp_dir().join(format!("january-cassette-{}", std::process::id()));
+        let request = Client::new()
+            .get("https://example.com/page?appid=secret&q=1")
+            .build()
+            .unwrap();
+
+        let recording = Cassette {
+            dir: dir.clone(),
+            record: true,
+        };
+        let recorded = System::new().block_on(scope(recording, async {
+            let live = http::Response::builder()
+                .status(201)
+                .url(request.url().clone())
+                .header("content-type", "text/html")
+                .header("set-cookie", "session=secret")
+                .body(b"<p>hello</p>".to_vec())
+                .unwrap();
+
+            let resp = tape(&request)
+                .unwrap()
+                .record(Response::from(live))
+                .await
+                .unwrap();
+            let headers = resp.headers().clone();
+            (
+                resp.status(),
+                headers,
+                resp.url().clone(),
+                resp.bytes().await.unwrap(),
+            )
+        }));
+
+        let playback = Cassette {
+            dir: dir.clone(),
+            record: false,
+        };
+        let replayed = System::new().block_on(scope(playback, async {
+            let resp = tape(&request).unwrap().replay().unwrap().unwrap();
+            let headers = resp.headers().clone();
+            (
+                resp.status(),
+                headers,
+                resp.url().clone(),
+                resp.bytes().await.unwrap(),
+            )
+        }));
+
+        let written: String = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(recorded, replayed);
+        let (status, headers, url, body) = replayed;
+        assert_eq!(status.as_u16(), 201);
+        assert_eq!(headers.get("content-type").unwrap(), "text/html");
+        assert!(headers.get("set-cookie").is_none());
+        assert_eq!(url.as_str(), "https://example.com/page?q=1");
+        assert_eq!(&body[..], b"<p>hello</p>");
+        assert!(!written.contains("secret"));
+    }
+}
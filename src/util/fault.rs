@@ -0,0 +1,77 @@
+use log::info;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use reqwest::{Response, ResponseBuilderExt};
+use std::future::Future;
+use std::time::Duration;
+
+use super::result::Error;
+use super::variables::{Fault, FAULT_KINDS, FAULT_LATENCY, FAULT_RATE};
+
+/// Check the fault configuration ahead of the first request,
+/// returning the share of upstream requests which will fail.
+pub fn warm() -> f64 {
+    lazy_static::initialize(&FAULT_KINDS);
+    *FAULT_RATE
+}
+
+/// Pick a fault for the next upstream request, at the configured rate.
+fn pick() -> Option<Fault> {
+    let mut rng = rand::thread_rng();
+    if *FAULT_RATE <= 0.0 || !rng.gen_bool(FAULT_RATE.min(1.0)) {
+        return None;
+    }
+
+    FAULT_KINDS.choose(&mut rng).copied()
+}
+
+/// Run an upstream request, sometimes making it fail the way real
+/// upstreams do: slowly, not at all, or with a body cut short.
+pub async fn inject(
+    url: &str,
+    request: impl Future<Output = Result<Response, Error>>,
+) -> Result<Response, Error> {
+    let fault = match pick() {
+        Some(fault) => fault,
+        None => return request.await,
+    };
+
+    info!("Injecting {:?} into {}", fault, url);
+    let latency = Duration::from_millis(*FAULT_LATENCY);
+    match fault {
+        Fault::Latency => {
+            actix_web::rt::time::sleep(latency).await;
+            request.await
+        }
+        Fault::Timeout => {
+            actix_web::rt::time::sleep(latency).await;
+            Err(Error::RequestTimedOut)
+        }
+        Fault::Malformed => {
+            let resp = request.await?;
+            truncate(resp).await
+        }
+    }
+}
+
+/// Keep the status, headers and URL, but only half of the body.
+async fn truncate(resp: Response) -> Result<Response, Error> {
+    let mut response = http::Response::builder()
+        .status(resp.status())
+        .url(resp.url().clone());
+    for (name, value) in resp.headers() {
+        response = response.header(name, value);
+    }
+
+    let mut body = resp
+        .bytes()
+        .await
+        .map_err(|_| Error::ReqwestFailed)?
+        .to_vec();
+    body.truncate(body.len() / 2);
+
+    response
+        .body(body)
+        .map(Response::from)
+        .map_err(|_| Error::ReqwestFailed)
+}
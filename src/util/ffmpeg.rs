@@ -0,0 +1,328 @@
+use futures::channel::oneshot;
+use futures::Future;
+use mime::VIDEO;
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::{self, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+use super::load;
+use super::request::{consume_bytes, fetch};
+use super::result::Error;
+use super::variables::{
+    FFMPEG, FFPROBE, MAX_TRANSCODES, MAX_VIDEO_SIZE, MEDIA_CACHE_DIR, MEDIA_CACHE_SIZE, PUBLIC_URL,
+};
+
+lazy_static! {
+    static ref TRANSCODES: Semaphore = Semaphore::new(*MAX_TRANSCODES);
+    static ref RUNS: AtomicUsize = AtomicUsize::new(0);
+}
+
+/// Run ffmpeg on bytes we already hold without blocking the executor.
+/// Only our own pipe may be opened, so inputs can neither read local
+/// files nor make requests of their own.
+async fn run(before: &[String], input: Vec<u8>, after: &[String]) -> Result<(), Error> {
+    let ffmpeg = FFMPEG.as_ref().ok_or(Error::TranscodeFailed)?;
+
+    let mut command = Command::new(ffmpeg);
+    command
+        .args(["-hide_banner", "-loglevel", "error", "-y"])
+        .args(["-protocol_whitelist", "pipe"])
+        .args(before)
+        .args(["-i", "pipe:0"])
+        .args(after)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    match execute(command, Some(input)).await {
+        Ok((status, _)) if status.success() => Ok(()),
+        _ => Err(Error::TranscodeFailed),
+    }
+}
+
+/// Tells the thread supervising a process to kill it once dropped,
+/// which happens when the request waiting on it is abandoned.
+struct Abandon(Arc<AtomicBool>);
+
+impl Drop for Abandon {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Run a command on its own thread, feeding it `stdin` and collecting
+/// whatever it writes to stdout if that was piped. Dropping the returned
+/// future kills the process, so a client going away doesn't leave a
+/// transcode running for nobody. Only `MAX_TRANSCODES` run at once.
+async fn execute(
+    mut command: Command,
+    stdin: Option<Vec<u8>>,
+) -> io::Result<(ExitStatus, Vec<u8>)> {
+    let _permit = TRANSCODES
+        .acquire()
+        .await
+        .map_err(|_| io::Error::from(io::ErrorKind::Interrupted))?;
+    let abandoned = Arc::new(AtomicBool::new(false));
+    let _abandon = Abandon(abandoned.clone());
+
+    let (sender, receiver) = oneshot::channel();
+    thread::spawn(move || {
+        let result = command.spawn().and_then(|mut child| {
+            if let (Some(mut pipe), Some(bytes)) = (child.stdin.take(), stdin) {
+                // ffmpeg may stop reading early, which isn't an error on our side.
+                thread::spawn(move || pipe.write_all(&bytes).ok());
+            }
+
+            let reader = child.stdout.take().map(|mut pipe| {
+                thread::spawn(move || {
+                    let mut output = Vec::new();
+                    pipe.read_to_end(&mut output).map(|_| output)
+                })
+            });
+
+            let status = loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+
+                if abandoned.load(Ordering::Relaxed) {
+                    child.kill().ok();
+                }
+
+                thread::sleep(Duration::from_millis(50));
+            };
+
+            let output = match reader {
+                Some(reader) => reader.join().unwrap_or_else(|_| Ok(Vec::new()))?,
+                None => Vec::new(),
+            };
+
+            Ok((status, output))
+        });
+
+        sender.send(result).ok();
+    });
+
+    receiver
+        .await
+        .unwrap_or_else(|_| Err(io::Error::from(io::ErrorKind::Interrupted)))
+}
+
+/// Removes an unfinished output once dropped, including when the request
+/// waiting on it is abandoned mid-transcode. Once moved into place
+/// there's nothing left to remove.
+struct Partial(PathBuf);
+
+impl Drop for Partial {
+    fn drop(&mut self) {
+        fs::remove_file(&self.0).ok();
+    }
+}
+
+/// Where the output for a given source is kept on disk.
+fn cache_path(source: &str, variant: &str, extension: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    (source, variant).hash(&mut hasher);
+    MEDIA_CACHE_DIR.join(format!("{:016x}.{}", hasher.finish(), extension))
+}
+
+/// Produce a file with ffmpeg, or reuse the one from a previous run for
+/// the same source URL. `input` is only awaited when there is no such
+/// file, and `before` and `after` are the options given ahead of and
+/// after it.
+async fn cached(
+    source: &str,
+    variant: &str,
+    extension: &str,
+    before: &[String],
+    input: impl Future<Output = Result<Vec<u8>, Error>>,
+    after: &[String],
+) -> Result<Vec<u8>, Error> {
+    let path = cache_path(source, variant, extension);
+    if let Ok(bytes) = fs::read(&path) {
+        return Ok(bytes);
+    }
+
+    let input = input.await?;
+    fs::create_dir_all(&*MEDIA_CACHE_DIR).map_err(|_| Error::TranscodeFailed)?;
+
+    // Written alongside and moved into place, so a half finished file
+    // is never served. Each run gets its own, so concurrent requests
+    // for the same output don't write over one another.
+    let run_id = RUNS.fetch_add(1, Ordering::Relaxed);
+    let partial =
+        Partial(path.with_extension(format!("{}.{}-{}.part", extension, process::id(), run_id)));
+    let mut after = after.to_vec();
+    after.push(partial.0.to_string_lossy().to_string());
+
+    run(before, input, &after).await?;
+    fs::rename(&partial.0, &path).map_err(|_| Error::TranscodeFailed)?;
+    let bytes = fs::read(&path).map_err(|_| Error::TranscodeFailed)?;
+    evict();
+    Ok(bytes)
+}
+
+/// Delete the oldest outputs until the cache fits in `MEDIA_CACHE_SIZE`.
+fn evict() {
+    let entries = match fs::read_dir(&*MEDIA_CACHE_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok()?;
+            let path = entry.path();
+            if !metadata.is_file() || path.extension() == Some("part".as_ref()) {
+                return None;
+            }
+
+            Some((metadata.modified().ok()?, metadata.len(), path))
+        })
+        .collect();
+
+    // Newest first, so whatever is left over once the budget runs out goes.
+    files.sort_by_key(|(modified, ..)| Reverse(*modified));
+    let mut total = 0;
+    for (_, size, path) in files {
+        total += size;
+        if total > *MEDIA_CACHE_SIZE {
+            fs::remove_file(path).ok();
+        }
+    }
+}
+
+/// Download a video for ffmpeg to read, as far as `MAX_VIDEO_SIZE`.
+async fn download(video: &str) -> Result<Vec<u8>, Error> {
+    let _permit = load::admit().await?;
+    let (resp, mime) = fetch(video).await?;
+    if mime.type_() != VIDEO {
+        return Err(Error::NotAllowedToProxy);
+    }
+
+    consume_bytes(resp, *MAX_VIDEO_SIZE).await
+}
+
+/// Extract a single JPEG frame from a video at the given offset in seconds.
+pub async fn poster(video: &str, timestamp: u32) -> Result<Vec<u8>, Error> {
+    let timestamp = timestamp.to_string();
+    let before = ["-ss".to_string(), timestamp.clone()];
+    let after = ["-frames:v", "1", "-f", "mjpeg"].map(str::to_string);
+
+    cached(video, &timestamp, "jpg", &before, download(video), &after).await
+}
+
+/// Transcode an animated GIF to H.264, which is a fraction of the size.
+pub async fn gif_to_mp4(url: &str, gif: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let after = [
+        "-movflags",
+        "+faststart",
+        "-pix_fmt",
+        "yuv420p",
+        // yuv420p needs even dimensions.
+        "-vf",
+        "scale=trunc(iw/2)*2:trunc(ih/2)*2",
+        "-c:v",
+        "libx264",
+        "-f",
+        "mp4",
+    ]
+    .map(str::to_string);
+
+    cached(url, "mp4", "mp4", &[], async { Ok(gif) }, &after).await
+}
+
+/// Link to a poster frame for a video, if thumbnails are enabled.
+pub fn poster_url(video: &str) -> Option<String> {
+    FFMPEG.as_ref()?;
+    let mut url = reqwest::Url::parse(&format!("{}/thumbnail", PUBLIC_URL.as_ref()?)).ok()?;
+    url.query_pairs_mut().append_pair("url", video);
+    Some(url.to_string())
+}
+
+/// What ffprobe reports about a video's streams.
+pub struct Streams {
+    pub width: isize,
+    pub height: isize,
+    pub framerate: Option<f64>,
+    pub audio: bool,
+}
+
+#[derive(Deserialize)]
+struct Stream {
+    codec_type: String,
+    width: Option<isize>,
+    height: Option<isize>,
+    avg_frame_rate: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<Stream>,
+}
+
+/// Read the dimensions, framerate and whether there is any audio from
+/// the start of a video. Like `run` only the pipe may be read, so files
+/// which keep their index at the end can't be probed.
+pub async fn probe(video: Vec<u8>) -> Result<Streams, Error> {
+    let ffprobe = FFPROBE.as_ref().ok_or(Error::TranscodeFailed)?;
+
+    let mut command = Command::new(ffprobe);
+    command
+        .args(["-hide_banner", "-loglevel", "error"])
+        .args(["-protocol_whitelist", "pipe"])
+        .args([
+            "-show_entries",
+            "stream=codec_type,width,height,avg_frame_rate",
+        ])
+        .args(["-of", "json", "pipe:0"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+
+    let output = match execute(command, Some(video)).await {
+        Ok((status, output)) if status.success() => output,
+        _ => return Err(Error::TranscodeFailed),
+    };
+
+    let output: ProbeOutput =
+        serde_json::from_slice(&output).map_err(|_| Error::TranscodeFailed)?;
+    let video = output
+        .streams
+        .iter()
+        .find(|stream| stream.codec_type == "video")
+        .ok_or(Error::TranscodeFailed)?;
+
+    Ok(Streams {
+        width: video.width.unwrap_or(0),
+        height: video.height.unwrap_or(0),
+        framerate: video.avg_frame_rate.as_deref().and_then(framerate),
+        audio: output
+            .streams
+            .iter()
+            .any(|stream| stream.codec_type == "audio"),
+    })
+}
+
+/// Turn a rational like `30000/1001` into frames per second, to two places.
+fn framerate(rate: &str) -> Option<f64> {
+    let (frames, seconds) = rate.split_once('/')?;
+    let (frames, seconds): (f64, f64) = (frames.parse().ok()?, seconds.parse().ok()?);
+    if frames <= 0.0 || seconds <= 0.0 {
+        return None;
+    }
+
+    Some((frames / seconds * 100.0).round() / 100.0)
+}
@@ -0,0 +1,429 @@
+use bumpalo::collections::{String, Vec};
+use bumpalo::Bump;
+
+use super::entities::ENTITIES;
+
+/// A `<meta>`, `<link>` or `<base>` tag, with attribute names lowercased
+/// and values unescaped.
+pub struct Tag<'a> {
+    pub name: &'a str,
+    attributes: Vec<'a, (&'a str, &'a str)>,
+}
+
+impl<'a> Tag<'a> {
+    /// Value of an attribute. Browsers keep the first when one is repeated.
+    pub fn attr(&self, name: &str) -> Option<&'a str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+/// The parts of a document embeds are built from.
+///
+/// Everything is either borrowed from the document or allocated in the
+/// arena it was scanned into, so it is all freed at once with the arena.
+pub struct Head<'a> {
+    arena: &'a Bump,
+    pub tags: Vec<'a, Tag<'a>>,
+    pub json_ld: Vec<'a, &'a str>,
+}
+
+impl<'a> Head<'a> {
+    /// Lowercase text in the arena, borrowing it when it already is.
+    pub fn lowercase(&self, text: &'a str) -> &'a str {
+        lowercase(self.arena, text)
+    }
+}
+
+// Elements whose contents are text rather than markup.
+const RAW_TEXT: &[&str] = &[
+    "script", "style", "title", "textarea", "noscript", "template",
+];
+
+/// Pull what embeds need out of a document in a single pass over its
+/// tags, without building a DOM. Only the tags we read are kept, and
+/// everything else is skipped over as soon as its name is known.
+pub fn scan<'a>(html: &'a str, arena: &'a Bump) -> Head<'a> {
+    let bytes = html.as_bytes();
+    let mut head = Head {
+        arena,
+        tags: Vec::new_in(arena),
+        json_ld: Vec::new_in(arena),
+    };
+    let mut at = 0;
+
+    while let Some(offset) = html[at..].find('<') {
+        at += offset + 1;
+        let rest = &html[at..];
+
+        if rest.starts_with("!--") {
+            at = find(html, at + 3, "-->").map_or(bytes.len(), |end| end + 3);
+            continue;
+        }
+
+        if rest.starts_with('!') || rest.starts_with('?') || rest.starts_with('/') {
+            at = find(html, at, ">").map_or(bytes.len(), |end| end + 1);
+            continue;
+        }
+
+        let name_end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == ':'))
+            .unwrap_or(rest.len());
+        if name_end == 0 {
+            continue;
+        }
+
+        let name = lowercase(arena, &rest[..name_end]);
+        let (attributes, end) = attributes(html, at + name_end, arena);
+        at = end;
+
+        match name {
+            "meta" | "link" | "base" => head.tags.push(Tag { name, attributes }),
+            _ if RAW_TEXT.contains(&name) => {
+                let close = format!("</{}", name);
+                let text_end = find_ignore_case(html, at, &close).unwrap_or(bytes.len());
+                let text = &html[at..text_end];
+
+                if name == "script" {
+                    let kind = attributes
+                        .iter()
+                        .find(|(key, _)| *key == "type")
+                        .map(|(_, value)| value.trim());
+                    if matches!(kind, Some(kind) if kind.eq_ignore_ascii_case("application/ld+json"))
+                    {
+                        head.json_ld.push(text);
+                    }
+                }
+
+                at = find(html, text_end, ">").map_or(bytes.len(), |end| end + 1);
+            }
+            _ => {}
+        }
+    }
+
+    head
+}
+
+/// Read the attributes of a tag starting at `at`, returning them along
+/// with where the tag ends.
+fn attributes<'a>(
+    html: &'a str,
+    mut at: usize,
+    arena: &'a Bump,
+) -> (Vec<'a, (&'a str, &'a str)>, usize) {
+    let bytes = html.as_bytes();
+    let mut attributes = Vec::new_in(arena);
+
+    loop {
+        while at < bytes.len() && (bytes[at].is_ascii_whitespace() || bytes[at] == b'/') {
+            at += 1;
+        }
+
+        if at >= bytes.len() {
+            return (attributes, at);
+        }
+
+        if bytes[at] == b'>' {
+            return (attributes, at + 1);
+        }
+
+        let name_start = at;
+        while at < bytes.len()
+            && !matches!(bytes[at], b'=' | b'>' | b'/')
+            && !bytes[at].is_ascii_whitespace()
+        {
+            at += 1;
+        }
+
+        let name = lowercase(arena, &html[name_start..at]);
+        while at < bytes.len() && bytes[at].is_ascii_whitespace() {
+            at += 1;
+        }
+
+        let value = if at < bytes.len() && bytes[at] == b'=' {
+            at += 1;
+            while at < bytes.len() && bytes[at].is_ascii_whitespace() {
+                at += 1;
+            }
+
+            match bytes.get(at) {
+                Some(quote @ (b'"' | b'\'')) => {
+                    let start = at + 1;
+                    let end = html[start..]
+                        .find(*quote as char)
+                        .map_or(bytes.len(), |end| start + end);
+                    at = (end + 1).min(bytes.len());
+                    &html[start..end]
+                }
+                _ => {
+                    let start = at;
+                    while at < bytes.len() && bytes[at] != b'>' && !bytes[at].is_ascii_whitespace()
+                    {
+                        at += 1;
+                    }
+
+                    &html[start..at]
+                }
+            }
+        } else {
+            ""
+        };
+
+        if !name.is_empty() {
+            attributes.push((name, unescape(value, arena)));
+        }
+    }
+}
+
+fn find(html: &str, from: usize, needle: &str) -> Option<usize> {
+    html.get(from..)?.find(needle).map(|offset| from + offset)
+}
+
+fn find_ignore_case(html: &str, from: usize, needle: &str) -> Option<usize> {
+    html.as_bytes()
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+        .map(|offset| from + offset)
+}
+
+fn lowercase<'a>(arena: &'a Bump, text: &'a str) -> &'a str {
+    if text.bytes().any(|b| b.is_ascii_uppercase()) {
+        let mut lower = String::from_str_in(text, arena);
+        lower.make_ascii_lowercase();
+        lower.into_bump_str()
+    } else {
+        text
+    }
+}
+
+/// Decode character references, keeping the original text when there are none.
+fn unescape<'a>(text: &'a str, arena: &'a Bump) -> &'a str {
+    if !text.contains('&') {
+        return text;
+    }
+
+    let mut output = String::with_capacity_in(text.len(), arena);
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        // The longest named reference is 32 characters with its semicolon.
+        match rest.find(';').filter(|end| *end <= 33) {
+            Some(end) if reference(&rest[1..end], &mut output) => rest = &rest[end + 1..],
+            _ => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output.into_bump_str()
+}
+
+/// Push the text a character reference stands for, given without its
+/// `&` and `;`, returning whether it was one at all.
+fn reference(entity: &str, output: &mut String) -> bool {
+    if let Some(number) = entity.strip_prefix('#') {
+        let c = match number.strip_prefix(|c| c == 'x' || c == 'X') {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => number.parse().ok(),
+        }
+        .and_then(char::from_u32);
+
+        return match c {
+            Some(c) => {
+                output.push(c);
+                true
+            }
+            None => false,
+        };
+    }
+
+    match ENTITIES.binary_search_by(|(name, _)| name.cmp(&entity)) {
+        Ok(index) => {
+            output.push_str(ENTITIES[index].1);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content<'a>(head: &Head<'a>, property: &str) -> Option<&'a str> {
+        head.tags
+            .iter()
+            .find(|tag| tag.attr("property") == Some(property))
+            .and_then(|tag| tag.attr("content"))
+    }
+
+    #[test]
+    fn skips_comments() {
+        let arena = Bump::new();
+        let head = scan(
+            r#"<!-- <meta property="og:title" content="commented"> --><meta property="og:title" content="real">"#,
+            &arena,
+        );
+        assert_eq!(head.tags.len(), 1);
+        assert_eq!(content(&head, "og:title"), Some("real"));
+    }
+
+    #[test]
+    fn skips_raw_text() {
+        let arena = Bump::new();
+        let head = scan(
+            r#"<script>document.write("<meta property='og:title' content='script'>")</script>
+            <style>a::after { content: "<meta property='og:title' content='style'>" }</style>
+            <title><meta property="og:title" content="title"></title>
+            <noscript><meta property="og:title" content="noscript"></noscript>
+            <meta property="og:title" content="real">"#,
+            &arena,
+        );
+        assert_eq!(head.tags.len(), 1);
+        assert_eq!(content(&head, "og:title"), Some("real"));
+    }
+
+    #[test]
+    fn closes_raw_text_ignoring_case() {
+        let arena = Bump::new();
+        let head = scan(
+            r#"<SCRIPT type="application/ld+json">{"@type":"Thing"}</Script><meta property="og:title" content="after">"#,
+            &arena,
+        );
+        assert_eq!(head.json_ld.as_slice(), [r#"{"@type":"Thing"}"#]);
+        assert_eq!(content(&head, "og:title"), Some("after"));
+    }
+
+    #[test]
+    fn reads_unquoted_attributes() {
+        let arena = Bump::new();
+        let head = scan(
+            "<META Property=og:title CONTENT=bare/><meta property=og:type content=website>",
+            &arena,
+        );
+        assert_eq!(content(&head, "og:title"), Some("bare/"));
+        assert_eq!(content(&head, "og:type"), Some("website"));
+    }
+
+    #[test]
+    fn keeps_the_first_repeated_attribute() {
+        let arena = Bump::new();
+        let head = scan(
+            r#"<meta property="og:title" content="first" content="second">"#,
+            &arena,
+        );
+        assert_eq!(content(&head, "og:title"), Some("first"));
+    }
+
+    #[test]
+    fn survives_unterminated_tags() {
+        let arena = Bump::new();
+        let head = scan(r#"<meta property="og:title" content="cut off"#, &arena);
+        assert_eq!(content(&head, "og:title"), Some("cut off"));
+
+        // An attribute without a value is present but empty.
+        let head = scan(r#"<meta property="og:title" content"#, &arena);
+        assert_eq!(content(&head, "og:title"), Some(""));
+
+        let head = scan(
+            "<script>never closed <meta property=og:title content=no>",
+            &arena,
+        );
+        assert!(head.tags.is_empty());
+
+        let head = scan(
+            "<!-- never closed <meta property=og:title content=no>",
+            &arena,
+        );
+        assert!(head.tags.is_empty());
+    }
+
+    #[test]
+    fn decodes_references() {
+        let arena = Bump::new();
+        assert_eq!(unescape("Tom &amp; Jerry", &arena), "Tom & Jerry");
+        assert_eq!(
+            unescape("it&rsquo;s &mdash; fine&hellip;", &arena),
+            "it\u{2019}s \u{2014} fine\u{2026}"
+        );
+        assert_eq!(
+            unescape("&copy; 2021 &#39;quoted&#x27;", &arena),
+            "\u{a9} 2021 'quoted'"
+        );
+        assert_eq!(unescape("&NotEqualTilde;", &arena), "\u{2242}\u{338}");
+    }
+
+    #[test]
+    fn leaves_unknown_references() {
+        let arena = Bump::new();
+        assert_eq!(unescape("AT&T", &arena), "AT&T");
+        assert_eq!(
+            unescape("&notareference; &#xD800; &", &arena),
+            "&notareference; &#xD800; &"
+        );
+        assert_eq!(unescape("a & b; c", &arena), "a & b; c");
+    }
+
+    #[test]
+    fn entities_are_sorted() {
+        assert!(ENTITIES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    // Compare against building a DOM, as pages used to be parsed:
+    // SCAN_SPEED_PAGE=page.html cargo test --release scan_speed -- --ignored --nocapture
+    //
+    // Give it a page saved from a live site, as none are checked in.
+    // Without one, a fixture's head is followed by a body shaped like
+    // a real one, with markup and inline scripts the scan skips over.
+    #[test]
+    #[ignore]
+    fn scan_speed() {
+        use std::time::Instant;
+
+        let html = match std::env::var("SCAN_SPEED_PAGE") {
+            Ok(path) => std::fs::read_to_string(path).unwrap(),
+            Err(_) => {
+                let fixture = include_str!("../../snapshots/youtube.html");
+                let head = &fixture[..fixture.find("</head>").unwrap() + 7];
+                let item = "<div class=\"item\"><a href=\"/watch?v=abc\"><img src=\"/t.jpg\" alt=\"\"></a><span>Title &amp; more</span></div>\n";
+                let script = format!("<script>var data = {};</script>\n", "[1,2,3],".repeat(2000));
+                format!(
+                    "{}<body>{}{}</body></html>",
+                    head,
+                    item.repeat(4000),
+                    script.repeat(10)
+                )
+            }
+        };
+        let runs = 100;
+
+        let started = Instant::now();
+        for _ in 0..runs {
+            let arena = Bump::new();
+            assert!(!scan(&html, &arena).tags.is_empty());
+        }
+        let scanned = started.elapsed() / runs;
+
+        let selector = scraper::Selector::parse("meta, link, base").unwrap();
+        let started = Instant::now();
+        for _ in 0..runs {
+            let document = scraper::Html::parse_document(&html);
+            assert!(document.select(&selector).next().is_some());
+        }
+        let parsed = started.elapsed() / runs;
+
+        println!(
+            "scan {:?}, DOM {:?} per {} byte document",
+            scanned,
+            parsed,
+            html.len()
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use image::{DynamicImage, ImageOutputFormat, RgbImage};
+use libheif_rs::{ColorSpace, HeifContext, RgbChroma};
+
+use super::result::Error;
+
+/// Decode a HEIC/HEIF image and re-encode it as a JPEG browsers can display.
+pub fn to_jpeg(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let context = HeifContext::read_from_bytes(bytes).map_err(|_| Error::CouldNotDecodeImage)?;
+    let image = context
+        .primary_image_handle()
+        .and_then(|handle| handle.decode(ColorSpace::Rgb(RgbChroma::Rgb), false))
+        .map_err(|_| Error::CouldNotDecodeImage)?;
+
+    let planes = image.planes();
+    let plane = planes.interleaved.ok_or(Error::CouldNotDecodeImage)?;
+
+    // Rows may be padded out past the width of the image.
+    let row = plane.width as usize * 3;
+    let mut pixels = Vec::with_capacity(row * plane.height as usize);
+    for line in plane.data.chunks(plane.stride).take(plane.height as usize) {
+        pixels.extend_from_slice(line.get(..row).ok_or(Error::CouldNotDecodeImage)?);
+    }
+
+    let image =
+        RgbImage::from_raw(plane.width, plane.height, pixels).ok_or(Error::CouldNotDecodeImage)?;
+
+    let mut output = Vec::new();
+    DynamicImage::ImageRgb8(image)
+        .write_to(&mut output, ImageOutputFormat::Jpeg(85))
+        .map_err(|_| Error::CouldNotDecodeImage)?;
+
+    Ok(output)
+}
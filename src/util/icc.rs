@@ -0,0 +1,437 @@
+use image::{DynamicImage, RgbaImage};
+
+use super::result::Error;
+
+/// Colour profile embedded in an encoded image, kept in the
+/// container's own representation so it can be copied verbatim.
+enum Profile<'a> {
+    // APP2 segments, the profile may be split across several.
+    Jpeg(Vec<&'a [u8]>),
+    // The whole iCCP chunk, which is already compressed.
+    Png(&'a [u8]),
+    // The profile itself, from a WebP ICCP chunk. Nothing we encode to
+    // can carry it, so those images are converted to sRGB instead.
+    WebP(&'a [u8]),
+}
+
+fn jpeg_profile(bytes: &[u8]) -> Result<Option<Profile<'_>>, Error> {
+    let mut segments = Vec::new();
+    let mut offset = 2;
+    while offset + 4 <= bytes.len() && bytes[offset] == 0xFF {
+        let marker = bytes[offset + 1];
+        // Start of scan, only image data follows.
+        if marker == 0xDA {
+            break;
+        }
+
+        let length = u16::from_be_bytes([bytes[offset + 2], bytes[offset + 3]]) as usize;
+        let segment = bytes
+            .get(offset..offset + 2 + length)
+            .ok_or(Error::CouldNotDecodeImage)?;
+        if marker == 0xE2 && segment.len() > 4 && segment[4..].starts_with(b"ICC_PROFILE\0") {
+            segments.push(segment);
+        }
+
+        offset += 2 + length;
+    }
+
+    if segments.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(Profile::Jpeg(segments)))
+    }
+}
+
+fn png_profile(bytes: &[u8]) -> Result<Option<Profile<'_>>, Error> {
+    let mut offset = 8;
+    while offset + 8 <= bytes.len() {
+        let length = u32::from_be_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ]) as usize;
+        let kind = &bytes[offset + 4..offset + 8];
+        if kind == b"IDAT" {
+            break;
+        }
+
+        let chunk = bytes
+            .get(offset..offset + 12 + length)
+            .ok_or(Error::CouldNotDecodeImage)?;
+        if kind == b"iCCP" {
+            return Ok(Some(Profile::Png(chunk)));
+        }
+
+        offset += 12 + length;
+    }
+
+    Ok(None)
+}
+
+fn webp_profile(bytes: &[u8]) -> Option<Profile<'_>> {
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let kind = &bytes[offset..offset + 4];
+        let length = u32::from_le_bytes([
+            bytes[offset + 4],
+            bytes[offset + 5],
+            bytes[offset + 6],
+            bytes[offset + 7],
+        ]) as usize;
+        if kind == b"ICCP" {
+            return bytes
+                .get(offset + 8..offset + 8 + length)
+                .map(Profile::WebP);
+        }
+
+        offset += 8 + length + length % 2;
+    }
+
+    None
+}
+
+fn profile(bytes: &[u8]) -> Result<Option<Profile<'_>>, Error> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        jpeg_profile(bytes)
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_profile(bytes)
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        Ok(webp_profile(bytes))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Copy the colour profile of `source` onto a re-encoded `output`, as
+/// the encoders drop it and wide-gamut photos would be shown washed out.
+///
+/// Returns `None` if the source has a profile which can't be carried over,
+/// in which case the original should be served instead, and an error if
+/// its segments are cut short, as the profile can't be told apart then.
+pub fn transfer(source: &[u8], output: Vec<u8>) -> Result<Option<Vec<u8>>, Error> {
+    Ok(match profile(source)? {
+        None => Some(output),
+        // The encoder starts with its own JFIF APP0, which must stay first.
+        Some(Profile::Jpeg(segments)) if output.starts_with(&[0xFF, 0xD8]) => {
+            let start = match output.get(2..6) {
+                Some([0xFF, 0xE0, high, low]) => 4 + u16::from_be_bytes([*high, *low]) as usize,
+                _ => 2,
+            };
+
+            let header = match output.get(..start) {
+                Some(header) => header,
+                None => return Ok(None),
+            };
+
+            let mut result = header.to_vec();
+            segments
+                .into_iter()
+                .for_each(|segment| result.extend_from_slice(segment));
+            result.extend_from_slice(&output[start..]);
+            Some(result)
+        }
+        // IHDR is always the first chunk, so the profile goes right after it.
+        Some(Profile::Png(chunk)) if output.starts_with(b"\x89PNG\r\n\x1a\n") => {
+            let mut result = match output.get(..33) {
+                Some(header) => header.to_vec(),
+                None => return Ok(None),
+            };
+            result.extend_from_slice(chunk);
+            result.extend_from_slice(&output[33..]);
+            Some(result)
+        }
+        // Already converted by `to_srgb`.
+        Some(Profile::WebP(_)) => Some(output),
+        Some(_) => None,
+    })
+}
+
+/// Convert a resized `image` to sRGB if `source` carries a profile which
+/// `transfer` can't copy over, so it looks right served without one.
+///
+/// Returns `None` if that profile isn't one we can interpret, as only
+/// matrix and curve profiles are understood, which is what cameras and
+/// editors embed.
+pub fn to_srgb(source: &[u8], image: DynamicImage) -> Result<Option<DynamicImage>, Error> {
+    let transform = match profile(source)? {
+        Some(Profile::WebP(icc)) => match Transform::parse(icc) {
+            Some(transform) => transform,
+            None => return Ok(None),
+        },
+        _ => return Ok(Some(image)),
+    };
+
+    let mut pixels: RgbaImage = image.into_rgba8();
+    for pixel in pixels.pixels_mut() {
+        let linear = [
+            transform.curves[0][pixel[0] as usize],
+            transform.curves[1][pixel[1] as usize],
+            transform.curves[2][pixel[2] as usize],
+        ];
+
+        for (channel, row) in transform.matrix.iter().enumerate() {
+            let value = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+            pixel[channel] = encode_srgb(value);
+        }
+    }
+
+    Ok(Some(DynamicImage::ImageRgba8(pixels)))
+}
+
+// From XYZ relative to the D50 white of profile connection space to
+// linear sRGB, Bradford adapted to its D65 white.
+const XYZ_TO_SRGB: [[f32; 3]; 3] = [
+    [3.133_856, -1.616_867, -0.490_615],
+    [-0.978_768, 1.916_142, 0.033_454],
+    [0.071_945, -0.228_991, 1.405_243],
+];
+
+/// What a matrix and curve profile does, flattened to decoding each
+/// channel to linear light and a single matrix into linear sRGB.
+struct Transform {
+    curves: [[f32; 256]; 3],
+    matrix: [[f32; 3]; 3],
+}
+
+impl Transform {
+    fn parse(icc: &[u8]) -> Option<Transform> {
+        // Only RGB data connected through XYZ can be handled by a matrix.
+        if icc.get(16..20)? != b"RGB " || icc.get(20..24)? != b"XYZ " {
+            return None;
+        }
+
+        let count = read_u32(icc, 128)? as usize;
+        let tag = |signature: &[u8]| {
+            (0..count.min(256)).find_map(|index| {
+                let entry = 132 + index * 12;
+                if icc.get(entry..entry + 4)? != signature {
+                    return None;
+                }
+
+                let offset = read_u32(icc, entry + 4)? as usize;
+                let size = read_u32(icc, entry + 8)? as usize;
+                icc.get(offset..offset.checked_add(size)?)
+            })
+        };
+
+        let mut primaries = [[0.0; 3]; 3];
+        for (column, signature) in [b"rXYZ", b"gXYZ", b"bXYZ"].iter().enumerate() {
+            let xyz = tag(*signature)?;
+            if xyz.get(..4)? != b"XYZ " {
+                return None;
+            }
+
+            for (row, primary) in primaries.iter_mut().enumerate() {
+                primary[column] = read_fixed(xyz, 8 + row * 4)?;
+            }
+        }
+
+        let mut curves = [[0.0; 256]; 3];
+        for (curve, signature) in curves.iter_mut().zip([b"rTRC", b"gTRC", b"bTRC"].iter()) {
+            let trc = tag(*signature)?;
+            for (input, value) in curve.iter_mut().enumerate() {
+                *value = apply_curve(trc, input as f32 / 255.0)?;
+            }
+        }
+
+        let mut matrix = [[0.0; 3]; 3];
+        for (row, output) in matrix.iter_mut().enumerate() {
+            for (column, value) in output.iter_mut().enumerate() {
+                *value = (0..3)
+                    .map(|index| XYZ_TO_SRGB[row][index] * primaries[index][column])
+                    .sum();
+            }
+        }
+
+        Some(Transform { curves, matrix })
+    }
+}
+
+/// Decode a channel value with a `curv` or `para` tone curve.
+fn apply_curve(trc: &[u8], input: f32) -> Option<f32> {
+    match trc.get(..4)? {
+        b"curv" => match read_u32(trc, 8)? {
+            0 => Some(input),
+            1 => Some(input.powf(read_u16(trc, 12)? as f32 / 256.0)),
+            entries => {
+                // A table, looked up with linear interpolation.
+                let last = entries as usize - 1;
+                let position = input * last as f32;
+                let index = (position as usize).min(last);
+                let next = (index + 1).min(last);
+                let low = read_u16(trc, 12 + index * 2)? as f32;
+                let high = read_u16(trc, 12 + next * 2)? as f32;
+                let fraction = position - index as f32;
+                Some((low + (high - low) * fraction) / 65535.0)
+            }
+        },
+        b"para" => {
+            let kind = read_u16(trc, 8)?;
+            let count = match kind {
+                0 => 1,
+                1 => 3,
+                2 => 4,
+                3 => 5,
+                4 => 7,
+                _ => return None,
+            };
+
+            let mut params = [0.0; 7];
+            for (index, param) in params.iter_mut().enumerate().take(count) {
+                *param = read_fixed(trc, 12 + index * 4)?;
+            }
+
+            let [g, a, b, c, d, e, f] = params;
+            Some(match kind {
+                0 => input.powf(g),
+                1 if input >= -b / a => (a * input + b).powf(g),
+                1 => 0.0,
+                2 if input >= -b / a => (a * input + b).powf(g) + c,
+                2 => c,
+                3 if input >= d => (a * input + b).powf(g),
+                3 => c * input,
+                _ if input >= d => (a * input + b).powf(g) + e,
+                _ => c * input + f,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn encode_srgb(linear: f32) -> u8 {
+    let linear = linear.clamp(0.0, 1.0);
+    let encoded = if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+
+    (encoded * 255.0).round() as u8
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([
+        *bytes.get(offset)?,
+        *bytes.get(offset + 1)?,
+    ]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// An s15Fixed16Number.
+fn read_fixed(bytes: &[u8], offset: usize) -> Option<f32> {
+    Some(read_u32(bytes, offset)? as i32 as f32 / 65536.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_truncated_segments() {
+        // An APP2 segment claiming more bytes than follow it.
+        let source = [0xFF, 0xD8, 0xFF, 0xE2, 0x01, 0x00, b'I', b'C', b'C'];
+        assert!(transfer(&source, vec![0xFF, 0xD8]).is_err());
+
+        // Without a profile the output is used as it is.
+        let source = [0xFF, 0xD8, 0xFF, 0xDA];
+        assert_eq!(
+            transfer(&source, vec![0xFF, 0xD8]).unwrap(),
+            Some(vec![0xFF, 0xD8])
+        );
+    }
+
+    #[test]
+    fn carries_profiles_after_the_jfif_header() {
+        let profile = [
+            0xFF, 0xE2, 0x00, 0x10, b'I', b'C', b'C', b'_', b'P', b'R', b'O', b'F', b'I', b'L',
+            b'E', 0, 1, 1,
+        ];
+        let mut source = vec![0xFF, 0xD8];
+        source.extend_from_slice(&profile);
+        source.extend_from_slice(&[0xFF, 0xDA]);
+
+        let app0 = [0xFF, 0xE0, 0x00, 0x04, 0, 0];
+        let mut output = vec![0xFF, 0xD8];
+        output.extend_from_slice(&app0);
+        output.extend_from_slice(&[0xFF, 0xDA]);
+
+        let mut expected = vec![0xFF, 0xD8];
+        expected.extend_from_slice(&app0);
+        expected.extend_from_slice(&profile);
+        expected.extend_from_slice(&[0xFF, 0xDA]);
+        assert_eq!(transfer(&source, output).unwrap(), Some(expected));
+    }
+
+    fn webp_with_profile(icc: &[u8]) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0".to_vec();
+        bytes.extend_from_slice(&[0; 10]);
+        bytes.extend_from_slice(b"ICCP");
+        bytes.extend_from_slice(&(icc.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(icc);
+        bytes
+    }
+
+    // sRGB primaries with a linear curve, so only the curve differs
+    // from sRGB itself.
+    fn linear_srgb() -> Vec<u8> {
+        let mut icc = vec![0; 128];
+        icc[16..20].copy_from_slice(b"RGB ");
+        icc[20..24].copy_from_slice(b"XYZ ");
+        icc.extend_from_slice(&6u32.to_be_bytes());
+
+        let primaries = [
+            (b"rXYZ", [0.436_075, 0.222_504, 0.013_932]),
+            (b"gXYZ", [0.385_065, 0.716_879, 0.097_105]),
+            (b"bXYZ", [0.143_080, 0.060_617, 0.714_173]),
+        ];
+        let data = 132 + 6 * 12;
+        let curve = data + primaries.len() * 20;
+        for (index, (signature, _)) in primaries.iter().enumerate() {
+            icc.extend_from_slice(*signature);
+            icc.extend_from_slice(&((data + index * 20) as u32).to_be_bytes());
+            icc.extend_from_slice(&20u32.to_be_bytes());
+        }
+        for signature in [b"rTRC", b"gTRC", b"bTRC"].iter() {
+            icc.extend_from_slice(*signature);
+            icc.extend_from_slice(&(curve as u32).to_be_bytes());
+            icc.extend_from_slice(&14u32.to_be_bytes());
+        }
+
+        for (_, xyz) in primaries.iter() {
+            icc.extend_from_slice(b"XYZ \0\0\0\0");
+            for value in xyz.iter() {
+                icc.extend_from_slice(&((value * 65536.0) as i32).to_be_bytes());
+            }
+        }
+        icc.extend_from_slice(b"curv\0\0\0\0\0\0\0\x01\x01\0");
+        icc
+    }
+
+    #[test]
+    fn converts_webp_profiles_to_srgb() {
+        let source = webp_with_profile(&linear_srgb());
+        let image = DynamicImage::ImageRgba8(
+            RgbaImage::from_raw(2, 1, vec![128, 128, 128, 255, 255, 0, 0, 7]).unwrap(),
+        );
+        let converted = to_srgb(&source, image).unwrap().unwrap().into_rgba8();
+
+        // Linear mid-grey is lighter once encoded for sRGB.
+        let grey = converted.get_pixel(0, 0);
+        assert!((187..=189).contains(&grey[0]) && grey[0] == grey[1] && grey[1] == grey[2]);
+        assert_eq!(grey[3], 255);
+
+        let red = converted.get_pixel(1, 0);
+        assert!(red[0] >= 254 && red[1] <= 1 && red[2] <= 1);
+        assert_eq!(red[3], 7);
+
+        // Profiles which can't be interpreted leave the original to be served.
+        let source = webp_with_profile(b"not a profile");
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(1, 1));
+        assert!(to_srgb(&source, image).unwrap().is_none());
+    }
+}
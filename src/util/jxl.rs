@@ -0,0 +1,132 @@
+/// Reads bits out of a codestream, least significant first.
+struct Bits<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Bits<'_> {
+    fn read(&mut self, count: usize) -> Option<u32> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = self.bytes.get((self.position + i) / 8)?;
+            value |= (((byte >> ((self.position + i) % 8)) & 1) as u32) << i;
+        }
+
+        self.position += count;
+        Some(value)
+    }
+
+    /// A dimension which isn't a multiple of eight.
+    fn dimension(&mut self) -> Option<u32> {
+        let bits = [9, 13, 18, 30][self.read(2)? as usize];
+        Some(1 + self.read(bits)?)
+    }
+}
+
+/// Find the bare codestream, unwrapping the ISO BMFF container if there is one.
+fn codestream(bytes: &[u8]) -> Option<&[u8]> {
+    if bytes.starts_with(&[0xFF, 0x0A]) {
+        return Some(bytes);
+    }
+
+    if !bytes.starts_with(b"\0\0\0\x0cJXL \r\n\x87\n") {
+        return None;
+    }
+
+    let mut offset = 0;
+    while offset + 8 <= bytes.len() {
+        let length = u32::from_be_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ]) as usize;
+        match &bytes[offset + 4..offset + 8] {
+            b"jxlc" => return bytes.get(offset + 8..),
+            // Partial codestreams are prefixed with their index.
+            b"jxlp" => return bytes.get(offset + 12..),
+            _ => {}
+        }
+
+        if length < 8 {
+            return None;
+        }
+
+        offset += length;
+    }
+
+    None
+}
+
+/// Read the dimensions from a JPEG XL image's size header,
+/// as `imagesize` doesn't understand the format yet.
+pub fn size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let codestream = codestream(bytes)?;
+    if !codestream.starts_with(&[0xFF, 0x0A]) {
+        return None;
+    }
+
+    let mut bits = Bits {
+        bytes: &codestream[2..],
+        position: 0,
+    };
+
+    let small = bits.read(1)? == 1;
+    let height = if small {
+        8 * (1 + bits.read(5)?)
+    } else {
+        bits.dimension()?
+    };
+
+    let width = match bits.read(3)? {
+        0 if small => 8 * (1 + bits.read(5)?),
+        0 => bits.dimension()?,
+        ratio => {
+            let (numerator, denominator) =
+                [(1, 1), (12, 10), (4, 3), (3, 2), (16, 9), (5, 4), (2, 1)][ratio as usize - 1];
+            (height as u64 * numerator / denominator) as u32
+        }
+    };
+
+    Some((width, height))
+}
+
+/// Decode a JPEG XL image and re-encode it as a PNG.
+#[cfg(feature = "jxl")]
+pub fn to_png(bytes: &[u8]) -> Result<Vec<u8>, super::result::Error> {
+    use image::{DynamicImage, ImageBuffer, ImageOutputFormat};
+    use jxl_oxide::JxlImage;
+
+    use super::result::Error;
+
+    let image = JxlImage::builder()
+        .read(bytes)
+        .map_err(|_| Error::CouldNotDecodeImage)?;
+    let frame = image
+        .render_frame(0)
+        .map_err(|_| Error::CouldNotDecodeImage)?
+        .image_all_channels();
+
+    let (width, height) = (frame.width() as u32, frame.height() as u32);
+    let pixels: Vec<u8> = frame
+        .buf()
+        .iter()
+        .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+        .collect();
+
+    let image = match frame.channels() {
+        1 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8),
+        2 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLumaA8),
+        3 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8),
+        4 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8),
+        _ => None,
+    }
+    .ok_or(Error::CouldNotDecodeImage)?;
+
+    let mut output = Vec::new();
+    image
+        .write_to(&mut output, ImageOutputFormat::Png)
+        .map_err(|_| Error::CouldNotDecodeImage)?;
+
+    Ok(output)
+}
@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::timeout;
+
+use super::result::Error;
+use super::variables::{MAX_CONCURRENT_REQUESTS, QUEUE_SIZE, QUEUE_TIMEOUT};
+
+lazy_static! {
+    static ref PERMITS: Semaphore = Semaphore::new(*MAX_CONCURRENT_REQUESTS);
+    static ref WAITING: AtomicUsize = AtomicUsize::new(0);
+}
+
+/// Counts a request as queued for as long as it's held, which
+/// includes requests abandoned while they wait.
+struct Queued;
+
+impl Queued {
+    fn join() -> Option<Queued> {
+        let waiting = WAITING.fetch_add(1, Ordering::SeqCst);
+        if waiting >= *QUEUE_SIZE {
+            WAITING.fetch_sub(1, Ordering::SeqCst);
+            None
+        } else {
+            Some(Queued)
+        }
+    }
+}
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        WAITING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Wait for a turn to make upstream requests, holding it until the
+/// permit is dropped. Once the queue is full requests are turned away
+/// straight away, rather than piling up and holding on to memory, and
+/// so are those which have waited `QUEUE_TIMEOUT` seconds already.
+///
+/// Taken only where upstream work starts, never while holding another,
+/// so that answers from the caches aren't held up behind it.
+pub async fn admit() -> Result<SemaphorePermit<'static>, Error> {
+    if let Ok(permit) = PERMITS.try_acquire() {
+        return Ok(permit);
+    }
+
+    let _queued = Queued::join().ok_or(Error::Overloaded)?;
+    timeout(Duration::from_secs(*QUEUE_TIMEOUT), PERMITS.acquire())
+        .await
+        .map_err(|_| Error::Overloaded)?
+        .map_err(|_| Error::Overloaded)
+}
@@ -0,0 +1,44 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::structs::special::Special;
+
+/// How often each special was produced since startup, to show which
+/// providers are worth improving and to notice when a site changes its
+/// links and a pattern quietly stops matching.
+#[derive(Clone, Default, Serialize)]
+pub struct Specials {
+    total: u64,
+    // Embeds no provider matched.
+    none: u64,
+    // Embeds where a provider matched but generating the special failed.
+    failed: u64,
+    matched: BTreeMap<String, u64>,
+}
+
+lazy_static! {
+    static ref SPECIALS: Mutex<Specials> = Mutex::new(Specials::default());
+}
+
+/// Account for the special generated for an embed.
+pub fn record<E>(special: Result<&Special, E>) {
+    // Variants are only named by their serialised tag.
+    let kind = special.map(|special| {
+        serde_json::to_value(special)
+            .ok()
+            .and_then(|value| value["type"].as_str().map(str::to_string))
+    });
+
+    let mut specials = SPECIALS.lock().unwrap();
+    specials.total += 1;
+    match kind {
+        Ok(Some(kind)) if kind == "None" => specials.none += 1,
+        Ok(Some(kind)) => *specials.matched.entry(kind).or_insert(0) += 1,
+        Ok(None) | Err(_) => specials.failed += 1,
+    }
+}
+
+pub fn snapshot() -> Specials {
+    SPECIALS.lock().unwrap().clone()
+}
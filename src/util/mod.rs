@@ -1,3 +1,22 @@
+pub mod bandwidth;
+pub mod cache;
+pub mod cassette;
+pub mod colour;
+pub mod entities;
+pub mod fault;
+pub mod ffmpeg;
+pub mod head;
+#[cfg(feature = "heic")]
+pub mod heic;
+pub mod icc;
+pub mod jxl;
+pub mod load;
+pub mod metrics;
+pub mod opt_out;
+pub mod quota;
+pub mod referer;
+pub mod reputation;
 pub mod request;
 pub mod result;
-pub mod variables;
\ No newline at end of file
+pub mod trace;
+pub mod variables;
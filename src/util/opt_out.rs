@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use super::cache::Cache;
+use super::request::fetch_head;
+use super::result::Error;
+use super::variables::{CACHE_TTL, OPT_OUT_WELL_KNOWN};
+
+lazy_static! {
+    static ref ORIGINS: Cache<String, bool> = Cache::new(1000, Duration::from_secs(*CACHE_TTL));
+}
+
+/// Whether the site has opted out of embeds by serving `/.well-known/no-embed`.
+pub async fn opted_out(url: &str) -> bool {
+    if !*OPT_OUT_WELL_KNOWN {
+        return false;
+    }
+
+    let origin = match reqwest::Url::parse(url) {
+        Ok(url) => url.origin().ascii_serialization(),
+        Err(_) => return false,
+    };
+
+    if let Some(opted_out) = ORIGINS.get(&origin) {
+        return opted_out;
+    }
+
+    // Single page apps answer every path with their index,
+    // so a HTML response isn't taken as opting out.
+    let opted_out = match fetch_head(&format!("{}/.well-known/no-embed", origin)).await {
+        Ok((_, mime)) => mime.subtype() != mime::HTML,
+        Err(Error::MissingContentType) => true,
+        Err(_) => false,
+    };
+
+    ORIGINS.insert(origin, opted_out);
+    opted_out
+}
@@ -0,0 +1,131 @@
+use actix_web::dev::{ConnectionInfo, ServiceRequest};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use lru::LruCache;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::variables::{API_KEYS, DAILY_QUOTA, QUOTA_CLIENTS, TRUSTED_PROXIES};
+
+const DAY: u64 = 86400;
+
+lazy_static! {
+    // Counts are only kept for the current UTC day, and for the clients
+    // seen most recently once there are more than `QUOTA_CLIENTS`.
+    static ref USAGE: Mutex<(u64, LruCache<String, u32>)> =
+        Mutex::new((0, LruCache::new(*QUOTA_CLIENTS)));
+}
+
+/// A client's standing against the daily quota, after counting this request.
+pub struct Usage {
+    limit: u32,
+    used: u32,
+    reset: u64,
+}
+
+impl Usage {
+    pub fn exceeded(&self) -> bool {
+        self.used > self.limit
+    }
+
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        for (name, value) in [
+            ("x-ratelimit-limit", self.limit),
+            (
+                "x-ratelimit-remaining",
+                self.limit.saturating_sub(self.used),
+            ),
+            ("x-ratelimit-reset", self.reset as u32),
+        ] {
+            headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+        }
+    }
+}
+
+/// Compare a presented secret against a known one in time that doesn't
+/// depend on where they first differ.
+pub fn same_secret(presented: &[u8], known: &[u8]) -> bool {
+    presented.len() == known.len()
+        && presented
+            .iter()
+            .zip(known)
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+/// Identify the client behind a request, by the API key they present or
+/// otherwise their address. Only keys listed in `JANUARY_API_KEYS` count,
+/// so a made up header can't be used for a fresh quota. Keys are hashed
+/// so they are never held or reported in the clear.
+pub fn client(
+    headers: &HeaderMap,
+    peer: Option<SocketAddr>,
+    connection: &ConnectionInfo,
+) -> Option<String> {
+    if let Some(key) = api_key(headers) {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        return Some(format!("key:{:016x}", hasher.finish()));
+    }
+
+    // The port changes per connection, so only the address is kept.
+    let peer = peer?.ip();
+    if !TRUSTED_PROXIES.contains(&peer) {
+        return Some(peer.to_string());
+    }
+
+    // Behind a proxy we trust, the address it forwarded is the client's.
+    // It may come without a port, IPv6 with or without brackets.
+    let addr = connection.realip_remote_addr()?;
+    let ip = addr
+        .parse::<SocketAddr>()
+        .map(|addr| addr.ip())
+        .or_else(|_| {
+            addr.trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+        });
+
+    Some(match ip {
+        Ok(ip) => ip.to_string(),
+        Err(_) => addr.to_string(),
+    })
+}
+
+fn api_key(headers: &HeaderMap) -> Option<&'static str> {
+    let presented = headers.get(AUTHORIZATION)?.as_bytes();
+    API_KEYS
+        .iter()
+        .find(|key| same_secret(presented, key.as_bytes()))
+        .map(String::as_str)
+}
+
+/// Seconds since the epoch and the current UTC day.
+pub fn today() -> Option<(u64, u64)> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some((now, now / DAY))
+}
+
+/// Count a request against its client's quota.
+pub fn consume(req: &ServiceRequest) -> Option<Usage> {
+    let limit = (*DAILY_QUOTA)?;
+    let client = client(req.headers(), req.peer_addr(), &req.connection_info())?;
+    let (now, today) = today()?;
+
+    let mut usage = USAGE.lock().unwrap();
+    if usage.0 != today {
+        usage.0 = today;
+        usage.1.clear();
+    }
+
+    let used = usage.1.get(&client).copied().unwrap_or(0).saturating_add(1);
+    usage.1.put(client, used);
+
+    Some(Usage {
+        limit,
+        used,
+        reset: (today + 1) * DAY - now,
+    })
+}
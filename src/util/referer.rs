@@ -0,0 +1,53 @@
+use mime::Mime;
+use reqwest::{Response, Url};
+use std::time::Duration;
+
+use super::cache::Cache;
+use super::request::{fetch, fetch_if_modified, fetch_with_header};
+use super::result::Error;
+use super::variables::{CACHE_TTL, PROXY_CACHE_SIZE, REFERER_HOSTS};
+
+lazy_static! {
+    // Page each image was found on, for hosts which check the Referer.
+    static ref PAGES: Cache<String, String> =
+        Cache::new(*PROXY_CACHE_SIZE, Duration::from_secs(*CACHE_TTL));
+}
+
+fn requires_referer(url: &str) -> bool {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase))
+        .map(|host| REFERER_HOSTS.contains(&host))
+        .unwrap_or(false)
+}
+
+/// Remember which page an image was found on, if its host needs to know.
+pub fn remember(image: &str, page: &str) {
+    if requires_referer(image) {
+        PAGES.insert(image.to_string(), page.to_string());
+    }
+}
+
+/// Fetch an image, passing the page it was found on as the Referer
+/// for hosts with hotlink protection.
+pub async fn fetch_image(url: &str) -> Result<(Response, Mime), Error> {
+    match PAGES.get(&url.to_string()) {
+        Some(page) => fetch_with_header(url, "Referer", &page).await,
+        None => fetch(url).await,
+    }
+}
+
+/// Like `fetch_image`, sending the `validators` of a copy we already
+/// have and answering `None` when that copy is still current.
+pub async fn refetch_image(
+    url: &str,
+    validators: &[(&str, &str)],
+) -> Result<Option<(Response, Mime)>, Error> {
+    let page = PAGES.get(&url.to_string());
+    let mut headers = validators.to_vec();
+    if let Some(page) = &page {
+        headers.push(("Referer", page));
+    }
+
+    fetch_if_modified(url, &headers).await
+}
@@ -0,0 +1,132 @@
+use futures::future::{join_all, BoxFuture};
+use log::warn;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+pub use january_types::Threat;
+
+use super::request::{consume_json, post_json};
+use super::variables::{MALWARE_BLOCKLIST, PHISHING_BLOCKLIST, REPUTATION_HOOK};
+
+/// Something which knows whether a link is dangerous to follow,
+/// such as a Safe Browsing lookup or a phishing blocklist. Checks
+/// are consulted before anything is fetched from the link.
+pub trait Reputation: Send + Sync {
+    fn check<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Option<Threat>>;
+}
+
+#[derive(Deserialize)]
+struct HookResponse {
+    warning: Option<Threat>,
+}
+
+/// Asks an operator run service, which is sent `{ "url": .. }` and
+/// answers with `{ "warning": "phishing" }` or `"malware"` for bad links.
+pub struct Hook {
+    url: String,
+}
+
+impl Reputation for Hook {
+    fn check<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Option<Threat>> {
+        Box::pin(async move {
+            let result = match post_json(&self.url, &json!({ "url": url })).await {
+                Ok((resp, _)) => consume_json::<HookResponse>(resp).await,
+                Err(error) => Err(error),
+            };
+
+            // An unreachable hook shouldn't take embeds down with it.
+            match result {
+                Ok(response) => response.warning,
+                Err(error) => {
+                    warn!("Reputation hook failed for {}: {:?}", url, error);
+                    None
+                }
+            }
+        })
+    }
+}
+
+/// Hosts listed one per line in a local file, which also covers
+/// their subdomains. Blank lines and `#` comments are ignored.
+pub struct Blocklist {
+    hosts: HashSet<String>,
+    threat: Threat,
+}
+
+impl Blocklist {
+    pub fn load(path: &Path, threat: Threat) -> Blocklist {
+        let list = fs::read_to_string(path)
+            .unwrap_or_else(|_| panic!("Could not read blocklist {}.", path.display()));
+
+        Blocklist {
+            hosts: list
+                .lines()
+                .map(|line| line.split('#').next().unwrap_or_default().trim())
+                .filter(|host| !host.is_empty())
+                .map(|host| host.trim_end_matches('.').to_lowercase())
+                .collect(),
+            threat,
+        }
+    }
+
+    fn contains(&self, host: &str) -> bool {
+        let mut host = host;
+        loop {
+            if self.hosts.contains(host) {
+                return true;
+            }
+
+            match host.split_once('.') {
+                Some((_, parent)) => host = parent,
+                None => return false,
+            }
+        }
+    }
+}
+
+impl Reputation for Blocklist {
+    fn check<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Option<Threat>> {
+        let listed = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| self.contains(host)))
+            .unwrap_or(false);
+
+        Box::pin(async move { Some(self.threat).filter(|_| listed) })
+    }
+}
+
+lazy_static! {
+    static ref CHECKS: Vec<Box<dyn Reputation>> = {
+        let mut checks: Vec<Box<dyn Reputation>> = Vec::new();
+        if let Some(path) = PHISHING_BLOCKLIST.as_ref() {
+            checks.push(Box::new(Blocklist::load(path, Threat::Phishing)));
+        }
+
+        if let Some(path) = MALWARE_BLOCKLIST.as_ref() {
+            checks.push(Box::new(Blocklist::load(path, Threat::Malware)));
+        }
+
+        if let Some(url) = REPUTATION_HOOK.clone() {
+            checks.push(Box::new(Hook { url }));
+        }
+
+        checks
+    };
+}
+
+/// Load the configured blocklists ahead of the first request.
+pub fn warm() -> usize {
+    CHECKS.len()
+}
+
+/// The first threat any configured check flags the link for.
+pub async fn check(url: &str) -> Option<Threat> {
+    join_all(CHECKS.iter().map(|reputation| reputation.check(url)))
+        .await
+        .into_iter()
+        .flatten()
+        .next()
+}
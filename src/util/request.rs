@@ -1,25 +1,343 @@
 use mime::Mime;
-use reqwest::{header::CONTENT_TYPE, Client, Response};
-use scraper::Html;
+use percent_encoding::percent_decode_str;
+use reqwest::header::{
+    HeaderName, HeaderValue, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION,
+    PROXY_AUTHORIZATION, USER_AGENT,
+};
+use reqwest::{redirect, Client, Method, Request, RequestBuilder, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, Instant};
+use tokio::net::lookup_host;
 
+use super::cassette;
+use super::fault;
+use super::jxl;
+use super::reputation;
 use super::result::Error;
+use super::trace;
+use super::variables::{
+    ALLOW_PRIVATE_ADDRESSES, MAX_DOCUMENT_SIZE, MAX_IMAGE_SIZE, MAX_URL_LENGTH, POOL_IDLE_TIMEOUT,
+    POOL_MAX_IDLE_PER_HOST, REQUEST_TIMEOUT, TCP_KEEPALIVE, TRACE_HEADER,
+};
+
+// Largest image decoded, in pixels. Small files can declare huge
+// dimensions and take gigabytes to decode.
+pub const MAX_PIXELS: u64 = 64_000_000;
 
 lazy_static! {
+    // Shared between all routes so that connections (and TLS sessions)
+    // to frequently embedded hosts are kept alive and reused.
     static ref CLIENT: Client = reqwest::Client::builder()
         .user_agent(
             "Mozilla/5.0 (compatible; January/1.0; +https://gitlab.insrt.uk/revolt/january)"
         )
+        .pool_max_idle_per_host(*POOL_MAX_IDLE_PER_HOST)
+        .pool_idle_timeout(Duration::from_secs(*POOL_IDLE_TIMEOUT))
+        .tcp_keepalive(Duration::from_secs(*TCP_KEEPALIVE))
+        .timeout(Duration::from_secs(*REQUEST_TIMEOUT))
+        // Redirects are followed in `send`, so every hop's addresses are checked.
+        .redirect(redirect::Policy::none())
         .build()
         .unwrap();
 }
 
-pub async fn fetch(url: &str) -> Result<(Response, Mime), Error> {
-    let resp = CLIENT
-        .get(url)
-        .send()
+/// Build the shared client ahead of the first request.
+pub fn warm() {
+    lazy_static::initialize(&CLIENT);
+}
+
+/// Check a requested URL is something we are willing to fetch, before
+/// anything is sent. Credentials in the URL are refused so that links
+/// like `https://trusted.com@evil.com` can't pass for another site.
+///
+/// Returns the URL normalised the way it is sent upstream: spaces and
+/// Unicode are percent-encoded, existing escapes are left alone and
+/// international hostnames are converted to punycode.
+pub fn validate_url(url: &str) -> Result<String, Error> {
+    if url.len() > *MAX_URL_LENGTH || url.chars().any(char::is_control) {
+        return Err(Error::InvalidUrl);
+    }
+
+    let parsed = Url::parse(url.trim()).map_err(|_| Error::InvalidUrl)?;
+    if !matches!(parsed.scheme(), "http" | "https")
+        || parsed.host().is_none()
+        || !parsed.username().is_empty()
+        || parsed.password().is_some()
+    {
+        return Err(Error::InvalidUrl);
+    }
+
+    if !is_public_host(&parsed) {
+        return Err(Error::PrivateAddress);
+    }
+
+    Ok(parsed.to_string())
+}
+
+/// Whether a URL's host may be fetched, judging address literals only.
+/// Names are checked once resolved, by `check_destination`.
+fn is_public_host(url: &Url) -> bool {
+    if *ALLOW_PRIVATE_ADDRESSES {
+        return true;
+    }
+
+    // IPv6 literals are serialised in brackets.
+    match url
+        .host_str()
+        .map(|host| host.trim_start_matches('[').trim_end_matches(']'))
+        .and_then(|host| host.parse::<IpAddr>().ok())
+    {
+        Some(ip) => is_public_ip(ip),
+        None => true,
+    }
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => is_public_v6(ip),
+    }
+}
+
+/// Refuse a URL unless every address its host resolves to is public,
+/// so a name pointing at 127.0.0.1 or a metadata service is caught.
+async fn check_destination(url: &Url) -> Result<(), Error> {
+    if *ALLOW_PRIVATE_ADDRESSES || cassette::replaying() {
+        return Ok(());
+    }
+
+    if !is_public_host(url) {
+        return Err(Error::PrivateAddress);
+    }
+
+    let host = url.host_str().ok_or(Error::InvalidUrl)?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addresses: Vec<_> = lookup_host((host, port))
         .await
-        .map_err(|_| Error::ReqwestFailed)?;
+        .map_err(|_| Error::ReqwestFailed)?
+        .collect();
+
+    if addresses.is_empty() || !addresses.iter().all(|addr| is_public_ip(addr.ip())) {
+        return Err(Error::PrivateAddress);
+    }
+
+    Ok(())
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // Carrier-grade NAT, 100.64.0.0/10.
+        || (a == 100 && b & 0xc0 == 64)
+        || a == 0)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    // IPv4-mapped (::ffff:0:0/96) and NAT64 (64:ff9b::/96) addresses
+    // reach the embedded IPv4 address.
+    let segments = ip.segments();
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return is_public_v4(v4);
+    }
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [.., a, b, c, d] = ip.octets();
+        return is_public_v4(Ipv4Addr::new(a, b, c, d));
+    }
+
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local, fc00::/7.
+        || segments[0] & 0xfe00 == 0xfc00
+        // Link local, fe80::/10.
+        || segments[0] & 0xffc0 == 0xfe80
+        // Documentation, 2001:db8::/32.
+        || segments[..2] == [0x2001, 0xdb8])
+}
+
+/// Resolve a URL found on a page against the page's own URL.
+pub fn resolve_url(base: &str, href: &str) -> Option<String> {
+    Url::parse(base)
+        .and_then(|base| base.join(href.trim()))
+        .ok()
+        .map(|url| url.to_string())
+}
+
+pub async fn fetch(url: &str) -> Result<(Response, Mime), Error> {
+    send(CLIENT.get(url)).await
+}
+
+pub async fn fetch_head(url: &str) -> Result<(Response, Mime), Error> {
+    send(CLIENT.head(url)).await
+}
+
+pub async fn fetch_with_header(
+    url: &str,
+    name: &str,
+    value: &str,
+) -> Result<(Response, Mime), Error> {
+    send(CLIENT.get(url).header(name, value)).await
+}
+
+/// Fetch a page to embed, telling one that is gone apart from any
+/// other failure so it can be looked up in the archive instead.
+pub async fn fetch_page(url: &str, user_agent: Option<&str>) -> Result<(Response, Mime), Error> {
+    let request = match user_agent {
+        Some(user_agent) => CLIENT.get(url).header(USER_AGENT, user_agent),
+        None => CLIENT.get(url),
+    };
 
+    let resp = follow(request, true).await?;
+    if resp.status() == StatusCode::NOT_FOUND || resp.status() == StatusCode::GONE {
+        return Err(Error::NotFound);
+    }
+
+    accept(resp)
+}
+
+/// Fetch `url` again unless it is unchanged since the copy `headers`
+/// describe, with `If-None-Match` or `If-Modified-Since`, answering
+/// `None` when that copy is still current.
+pub async fn fetch_if_modified(
+    url: &str,
+    headers: &[(&str, &str)],
+) -> Result<Option<(Response, Mime)>, Error> {
+    let mut request = CLIENT.get(url);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+
+    let resp = follow(request, false).await?;
+    if resp.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+
+    accept(resp).map(Some)
+}
+
+pub async fn post_json(url: &str, body: &Value) -> Result<(Response, Mime), Error> {
+    send(json_request(url, body)).await
+}
+
+pub async fn post_json_with_header(
+    url: &str,
+    body: &Value,
+    name: &str,
+    value: &str,
+) -> Result<(Response, Mime), Error> {
+    send(json_request(url, body).header(name, value)).await
+}
+
+fn json_request(url: &str, body: &Value) -> RequestBuilder {
+    CLIENT
+        .post(url)
+        .header(CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+}
+
+const MAX_REDIRECTS: usize = 10;
+
+/// Where a redirect response points, resolved against the URL it came from.
+fn redirect_target(resp: &Response) -> Option<Url> {
+    if !resp.status().is_redirection() {
+        return None;
+    }
+
+    let location = resp.headers().get(LOCATION)?.to_str().ok()?;
+    resp.url().join(location).ok()
+}
+
+/// The request to send next when following a redirect, treating it the
+/// way browsers do: 301, 302 and 303 turn a POST into a bodyless GET.
+fn redirected(mut request: Request, status: StatusCode, location: Url) -> Request {
+    // Credentials meant for one host aren't passed on to another.
+    if request.url().host_str() != location.host_str() {
+        let headers = request.headers_mut();
+        headers.remove(AUTHORIZATION);
+        headers.remove(COOKIE);
+        headers.remove(PROXY_AUTHORIZATION);
+    }
+
+    if status != StatusCode::TEMPORARY_REDIRECT
+        && status != StatusCode::PERMANENT_REDIRECT
+        && request.method() == Method::POST
+    {
+        *request.method_mut() = Method::GET;
+        *request.body_mut() = None;
+        request.headers_mut().remove(CONTENT_TYPE);
+        request.headers_mut().remove(CONTENT_LENGTH);
+    }
+
+    *request.url_mut() = location;
+    request
+}
+
+async fn send(request: RequestBuilder) -> Result<(Response, Mime), Error> {
+    accept(follow(request, false).await?)
+}
+
+/// Send a request, following its redirects. Pages being embedded are
+/// `screen`ed, so a redirect to a flagged link is refused like the link.
+async fn follow(request: RequestBuilder, screen: bool) -> Result<Response, Error> {
+    // URLs found on pages are checked here as well as the requested one,
+    // and so is every redirect along the way.
+    let mut request = request.build().map_err(|_| Error::InvalidUrl)?;
+    let mut redirects = 0;
+    let resp = loop {
+        check_destination(request.url()).await?;
+        request.headers_mut().remove(TRACE_HEADER.as_str());
+        if let Some(value) = trace::header(request.url()) {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                let name = HeaderName::from_bytes(TRACE_HEADER.as_bytes())
+                    .map_err(|_| Error::ConversionFailed)?;
+                request.headers_mut().insert(name, value);
+            }
+        }
+
+        let next = request.try_clone();
+
+        let url = request.url().to_string();
+        let started = Instant::now();
+        let result = fault::inject(&url, cassette::execute(&CLIENT, request)).await;
+        trace::record(
+            &url,
+            result.as_ref().ok().map(|resp| resp.status().as_u16()),
+            started.elapsed(),
+        );
+
+        let resp = result?;
+        let location = match redirect_target(&resp) {
+            Some(location) => location,
+            None => break resp,
+        };
+
+        redirects += 1;
+        if redirects > MAX_REDIRECTS {
+            return Err(Error::ReqwestFailed);
+        }
+
+        if screen {
+            if let Some(warning) = reputation::check(location.as_str()).await {
+                return Err(Error::DangerousUrl { warning });
+            }
+        }
+
+        request = redirected(next.ok_or(Error::ReqwestFailed)?, resp.status(), location);
+    };
+
+    Ok(resp)
+}
+
+fn accept(resp: Response) -> Result<(Response, Mime), Error> {
     if !resp.status().is_success() {
         return Err(Error::RequestFailed);
     }
@@ -27,7 +345,7 @@ pub async fn fetch(url: &str) -> Result<(Response, Mime), Error> {
     let content_type = resp
         .headers()
         .get(CONTENT_TYPE)
-        .ok_or_else(|| Error::MissingContentType)?
+        .ok_or(Error::MissingContentType)?
         .to_str()
         .map_err(|_| Error::ConversionFailed)?;
 
@@ -37,19 +355,424 @@ pub async fn fetch(url: &str) -> Result<(Response, Mime), Error> {
     Ok((resp, mime))
 }
 
-pub async fn consume_fragment(resp: Response) -> Result<Html, Error> {
-    let body = resp.text().await.map_err(|_| Error::FailedToConsumeText)?;
-    Ok(Html::parse_document(&body))
+const HEAD_END: &[u8] = b"</head>";
+
+/// Read the document up to the end of its `<head>`.
+pub async fn consume_head(mut resp: Response) -> Result<String, Error> {
+    // Everything we care about lives in <head>, so stop reading as soon
+    // as it ends rather than buffering the whole document.
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(|_| Error::FailedToConsumeText)? {
+        let start = body.len().saturating_sub(HEAD_END.len());
+        body.extend_from_slice(&chunk);
+
+        if body[start..]
+            .windows(HEAD_END.len())
+            .any(|w| w.eq_ignore_ascii_case(HEAD_END))
+        {
+            break;
+        }
+
+        if body.len() > *MAX_DOCUMENT_SIZE {
+            return Err(Error::DocumentTooLarge);
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Read at most `limit` bytes of the body as text.
+pub async fn consume_text(mut resp: Response, limit: usize) -> Result<String, Error> {
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(|_| Error::FailedToConsumeText)? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= limit {
+            body.truncate(limit);
+            break;
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&body).to_string())
+}
+
+/// Read at most `limit` bytes of the body.
+pub async fn consume_bytes(mut resp: Response, limit: usize) -> Result<Vec<u8>, Error> {
+    let mut body = Vec::new();
+    while let Some(chunk) = resp
+        .chunk()
+        .await
+        .map_err(|_| Error::FailedToConsumeBytes)?
+    {
+        body.extend_from_slice(&chunk);
+        if body.len() >= limit {
+            body.truncate(limit);
+            break;
+        }
+    }
+
+    Ok(body)
+}
+
+/// Whether an encoded GIF, PNG or WebP image has more than one frame.
+pub fn is_animated(bytes: &[u8]) -> bool {
+    if bytes.starts_with(b"GIF8") {
+        gif_frames(bytes) > 1
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        has_animation_control(bytes)
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..16) == Some(b"WEBPVP8X") {
+        // The extended header carries an animation flag.
+        bytes
+            .get(20)
+            .map(|flags| flags & 0x02 != 0)
+            .unwrap_or(false)
+    } else {
+        false
+    }
 }
 
-pub async fn consume_size(resp: Response) -> Result<(isize, isize), Error> {
+/// Count the images in a GIF, walking its blocks so that bytes in
+/// the image data which look like block markers aren't mistaken for
+/// them. Stops at two, and at wherever the data is cut short.
+fn gif_frames(bytes: &[u8]) -> usize {
+    // Skip the sub-blocks which follow, returning where they end.
+    let sub_blocks = |mut at: usize| {
+        while let Some(&length) = bytes.get(at) {
+            at += 1;
+            if length == 0 {
+                return Some(at);
+            }
+            at += length as usize;
+        }
+        None
+    };
+
+    // The logical screen descriptor may be followed by a colour table.
+    let mut at = 13;
+    match bytes.get(10) {
+        Some(flags) if flags & 0x80 != 0 => at += 3 << ((flags & 0x07) + 1),
+        Some(_) => {}
+        None => return 0,
+    }
+
+    let mut frames = 0;
+    while frames < 2 {
+        at = match bytes.get(at) {
+            // Extension, its label and then data.
+            Some(0x21) => match sub_blocks(at + 2) {
+                Some(end) => end,
+                None => break,
+            },
+            // Image descriptor, an optional colour table then the
+            // LZW code size ahead of the data.
+            Some(0x2C) => {
+                frames += 1;
+                let flags = match bytes.get(at + 9) {
+                    Some(flags) => *flags,
+                    None => break,
+                };
+                let mut data = at + 10;
+                if flags & 0x80 != 0 {
+                    data += 3 << ((flags & 0x07) + 1);
+                }
+                match sub_blocks(data + 1) {
+                    Some(end) => end,
+                    None => break,
+                }
+            }
+            // The trailer, or something that isn't a GIF after all.
+            _ => break,
+        };
+    }
+
+    frames
+}
+
+/// Whether a PNG has an APNG animation control chunk, which has to come
+/// before the image data. Walks the chunks instead of searching, as
+/// the name could just as well appear inside compressed data.
+fn has_animation_control(bytes: &[u8]) -> bool {
+    let mut at = 8;
+    while let Some(header) = bytes.get(at..at + 8) {
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        match &header[4..] {
+            b"acTL" => return true,
+            b"IDAT" | b"IEND" => return false,
+            _ => {}
+        }
+
+        // Length, type, data and CRC.
+        at = match at.checked_add(length + 12) {
+            Some(next) => next,
+            None => return false,
+        };
+    }
+
+    false
+}
+
+/// What probing an image's header tells us about it.
+pub struct Probe {
+    pub width: isize,
+    pub height: isize,
+    pub animated: bool,
+    pub size_bytes: Option<u64>,
+    pub format: Option<&'static str>,
+}
+
+/// How much of an image is read to probe it. Enough for the header of
+/// anything `imagesize` knows, a JPEG XL codestream and a GIF's first
+/// few frames.
+const SIZE_PROBE: usize = 512_000;
+
+pub async fn consume_size(resp: Response) -> Result<Probe, Error> {
+    let length = resp.content_length();
+    let prefix = consume_bytes(resp, SIZE_PROBE).await?;
+    let mut probe = image_size(&prefix)?;
+    if length.is_some() || prefix.len() >= SIZE_PROBE {
+        // Without a Content-Length, the prefix is only the size if it
+        // was the whole body.
+        probe.size_bytes = length;
+    }
+
+    Ok(probe)
+}
+
+/// Width, height, format and whether an encoded image is animated.
+pub fn image_size(bytes: &[u8]) -> Result<Probe, Error> {
+    let (width, height, animated) = if let Ok(size) = imagesize::blob_size(bytes) {
+        (
+            size.width as isize,
+            size.height as isize,
+            is_animated(bytes),
+        )
+    } else if let Some((width, height)) = jxl::size(bytes) {
+        (width as isize, height as isize, false)
+    } else {
+        return Err(Error::CouldNotDetermineImageSize);
+    };
+
+    Ok(Probe {
+        width,
+        height,
+        animated,
+        size_bytes: Some(bytes.len() as u64),
+        format: image_format(bytes),
+    })
+}
+
+/// Short name of an encoded image's format, going by its magic bytes.
+pub fn image_format(bytes: &[u8]) -> Option<&'static str> {
+    use imagesize::ImageType;
+
+    if jxl::size(bytes).is_some() {
+        return Some("jxl");
+    }
+
+    Some(match imagesize::image_type(bytes).ok()? {
+        ImageType::Bmp => "bmp",
+        ImageType::Gif => "gif",
+        // AVIF shares the HEIF container, only the brand tells them apart.
+        ImageType::Heif => match bytes.get(8..12) {
+            Some(b"avif") | Some(b"avis") => "avif",
+            _ => "heif",
+        },
+        ImageType::Jpeg => "jpeg",
+        ImageType::Png => "png",
+        ImageType::Psd => "psd",
+        ImageType::Tiff => "tiff",
+        ImageType::Webp => "webp",
+    })
+}
+
+/// Decode the body of a `data:` URI, refusing anything larger than `limit`.
+pub fn decode_data_uri(uri: &str, limit: usize) -> Result<Vec<u8>, Error> {
+    let (header, data) = uri
+        .strip_prefix("data:")
+        .and_then(|uri| uri.split_once(','))
+        .ok_or(Error::InvalidUrl)?;
+
+    // Base64 needs four characters for every three bytes.
+    let base64 = header.ends_with(";base64");
+    let size = if base64 {
+        data.len() / 4 * 3
+    } else {
+        data.len()
+    };
+    if size > limit {
+        return Err(Error::DataUriNotAllowed);
+    }
+
+    if base64 {
+        base64::decode(data.trim()).map_err(|_| Error::CouldNotDecodeImage)
+    } else {
+        Ok(percent_decode_str(data).collect())
+    }
+}
+
+/// Probe an image and average it down to its dominant colour. That needs
+/// all of it, so no more than `MAX_IMAGE_SIZE` is read.
+pub async fn consume_colour(resp: Response) -> Result<(Probe, Option<String>), Error> {
+    let bytes = consume_bytes(resp, *MAX_IMAGE_SIZE + 1).await?;
+    if bytes.len() > *MAX_IMAGE_SIZE {
+        return Err(Error::ImageTooLarge);
+    }
+
+    let probe = image_size(&bytes)?;
+    let colour = if probe.width as u64 * probe.height as u64 > MAX_PIXELS {
+        None
+    } else {
+        dominant_colour(&bytes)
+    };
+
+    Ok((probe, colour))
+}
+
+pub fn dominant_colour(bytes: &[u8]) -> Option<String> {
+    let image = image::load_from_memory(bytes).ok()?;
+
+    // Averaging the image down to a single pixel gives a good
+    // enough approximation of the dominant colour.
+    let pixel = image.thumbnail_exact(1, 1).to_rgb8();
+    let [r, g, b] = pixel.get_pixel(0, 0).0;
+    Some(format!("#{:02x}{:02x}{:02x}", r, g, b))
+}
+
+pub async fn consume_json<T: DeserializeOwned>(resp: Response) -> Result<T, Error> {
     let bytes = resp
         .bytes()
         .await
         .map_err(|_| Error::FailedToConsumeBytes)?;
-    if let Ok(size) = imagesize::blob_size(&bytes) {
-        Ok((size.width as isize, size.height as isize))
-    } else {
-        Err(Error::CouldNotDetermineImageSize)
+    serde_json::from_slice(&bytes).map_err(|_| Error::FailedToConsumeJson)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public(url: &str) -> bool {
+        is_public_host(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn refuses_private_literals() {
+        assert!(!public("http://127.0.0.1/"));
+        assert!(!public("http://10.0.0.1/"));
+        assert!(!public("http://169.254.169.254/latest/meta-data"));
+        assert!(!public("http://[::1]/"));
+        assert!(!public("http://[fd00::1]/"));
+        assert!(!public("http://[fe80::1]/"));
+    }
+
+    #[test]
+    fn refuses_embedded_private_v4() {
+        assert!(!public("http://[::ffff:127.0.0.1]/"));
+        assert!(!public("http://[::ffff:a00:1]/"));
+        assert!(!public("http://[64:ff9b::10.0.0.1]/"));
+        assert!(!public("http://[64:ff9b::7f00:1]/"));
+    }
+
+    #[test]
+    fn refuses_carrier_grade_nat() {
+        assert!(!public("http://100.64.0.1/"));
+        assert!(!public("http://100.127.255.254/"));
+        assert!(public("http://100.128.0.1/"));
+    }
+
+    #[test]
+    fn allows_public_addresses() {
+        assert!(public("http://8.8.8.8/"));
+        assert!(public("http://[2606:4700::1111]/"));
+        assert!(public("http://[::ffff:8.8.8.8]/"));
+        assert!(public("http://[64:ff9b::8.8.8.8]/"));
+        assert!(public("https://example.com/"));
+    }
+
+    // A 1x1 GIF with a two colour global table, leaving off the trailer.
+    const GIF_HEADER: &[u8] = b"GIF89a\x01\x00\x01\x00\x80\x00\x00\x00\x00\x00\xff\xff\xff";
+    const GIF_CONTROL: &[u8] = b"\x21\xf9\x04\x00\x0a\x00\x00\x00";
+
+    fn gif_frame(data: &[u8]) -> Vec<u8> {
+        let mut frame = GIF_CONTROL.to_vec();
+        frame.extend_from_slice(b"\x2c\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02");
+        frame.push(data.len() as u8);
+        frame.extend_from_slice(data);
+        frame.push(0);
+        frame
+    }
+
+    fn png(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (kind, data) in chunks {
+            png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            png.extend_from_slice(*kind);
+            png.extend_from_slice(data);
+            png.extend_from_slice(&[0; 4]);
+        }
+        png
+    }
+
+    #[test]
+    fn counts_gif_frames() {
+        let mut still = GIF_HEADER.to_vec();
+        // Image data which happens to contain control extension markers.
+        still.extend(gif_frame(b"\x21\xf9\x21\xf9"));
+        still.push(0x3b);
+        assert!(!is_animated(&still));
+
+        let mut animated = GIF_HEADER.to_vec();
+        animated.extend(gif_frame(b"\x44\x01"));
+        animated.extend(gif_frame(b"\x44\x01"));
+        assert!(is_animated(&animated));
+
+        // Cut off partway through the second frame.
+        animated.truncate(animated.len() - 3);
+        assert!(is_animated(&animated));
+    }
+
+    #[test]
+    fn finds_apng_animation_control() {
+        let header: &[u8] = &[0; 13];
+        let animated = png(&[(b"IHDR", header), (b"acTL", &[0; 8]), (b"IDAT", b"")]);
+        assert!(is_animated(&animated));
+
+        let still = png(&[(b"IHDR", header), (b"IDAT", b"..acTL..")]);
+        assert!(!is_animated(&still));
+
+        let late = png(&[(b"IHDR", header), (b"IDAT", b""), (b"acTL", &[0; 8])]);
+        assert!(!is_animated(&late));
+    }
+
+    #[test]
+    fn drops_credentials_on_cross_host_redirects() {
+        let request = CLIENT
+            .post("https://a.example/")
+            .header(AUTHORIZATION, "secret")
+            .header(COOKIE, "session")
+            .body("form")
+            .build()
+            .unwrap();
+        let location = Url::parse("https://b.example/next").unwrap();
+        let request = redirected(request, StatusCode::FOUND, location);
+
+        assert_eq!(request.method(), Method::GET);
+        assert!(request.body().is_none());
+        assert!(request.headers().get(AUTHORIZATION).is_none());
+        assert!(request.headers().get(COOKIE).is_none());
+    }
+
+    #[test]
+    fn keeps_method_on_temporary_redirects() {
+        let request = CLIENT
+            .post("https://a.example/")
+            .header(AUTHORIZATION, "secret")
+            .body("form")
+            .build()
+            .unwrap();
+        let location = Url::parse("https://a.example/next").unwrap();
+        let request = redirected(request, StatusCode::TEMPORARY_REDIRECT, location);
+
+        assert_eq!(request.method(), Method::POST);
+        assert!(request.body().is_some());
+        assert!(request.headers().get(AUTHORIZATION).is_some());
     }
 }
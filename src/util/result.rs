@@ -1,22 +1,38 @@
-use actix_web::http::StatusCode;
+use actix_web::http::{header::RETRY_AFTER, StatusCode};
 use actix_web::{web::HttpResponse, ResponseError};
 use serde::Serialize;
 use serde_json;
 use std::fmt::Display;
 
-#[derive(Serialize, Debug)]
+use super::reputation::Threat;
+
+#[derive(Serialize, Debug, Clone)]
 #[serde(tag = "type")]
 pub enum Error {
     CouldNotDetermineImageSize,
+    CouldNotDecodeImage,
     FailedToParseContentType,
     FailedToConsumeBytes,
     FailedToConsumeText,
-    MetaSelectionFailed,
+    FailedToConsumeJson,
+    DocumentTooLarge,
+    ImageTooLarge,
     MissingContentType,
     NotAllowedToProxy,
+    InvalidUrl,
+    PrivateAddress,
+    DataUriNotAllowed,
+    DangerousUrl { warning: Threat },
+    Unauthorized,
     ConversionFailed,
+    TranscodeFailed,
+    ProviderFailed,
     ReqwestFailed,
     RequestFailed,
+    RequestTimedOut,
+    NotFound,
+    QuotaExceeded,
+    Overloaded,
     LabelMe,
 }
 
@@ -30,15 +46,29 @@ impl ResponseError for Error {
     fn status_code(&self) -> StatusCode {
         match &self {
             Error::CouldNotDetermineImageSize => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::CouldNotDecodeImage => StatusCode::INTERNAL_SERVER_ERROR,
             Error::FailedToParseContentType => StatusCode::INTERNAL_SERVER_ERROR,
             Error::FailedToConsumeBytes => StatusCode::INTERNAL_SERVER_ERROR,
             Error::FailedToConsumeText => StatusCode::INTERNAL_SERVER_ERROR,
-            Error::MetaSelectionFailed => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::FailedToConsumeJson => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::DocumentTooLarge => StatusCode::BAD_REQUEST,
+            Error::ImageTooLarge => StatusCode::BAD_REQUEST,
             Error::MissingContentType => StatusCode::BAD_REQUEST,
             Error::NotAllowedToProxy => StatusCode::BAD_REQUEST,
+            Error::InvalidUrl => StatusCode::BAD_REQUEST,
+            Error::PrivateAddress => StatusCode::FORBIDDEN,
+            Error::DataUriNotAllowed => StatusCode::BAD_REQUEST,
+            Error::DangerousUrl { .. } => StatusCode::FORBIDDEN,
+            Error::Unauthorized => StatusCode::UNAUTHORIZED,
             Error::ConversionFailed => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::TranscodeFailed => StatusCode::BAD_GATEWAY,
+            Error::ProviderFailed => StatusCode::BAD_GATEWAY,
             Error::ReqwestFailed => StatusCode::INTERNAL_SERVER_ERROR,
             Error::RequestFailed => StatusCode::BAD_REQUEST,
+            Error::RequestTimedOut => StatusCode::GATEWAY_TIMEOUT,
+            Error::NotFound => StatusCode::NOT_FOUND,
+            Error::QuotaExceeded => StatusCode::TOO_MANY_REQUESTS,
+            Error::Overloaded => StatusCode::SERVICE_UNAVAILABLE,
             Error::LabelMe => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -46,8 +76,12 @@ impl ResponseError for Error {
     fn error_response(&self) -> HttpResponse<actix_web::dev::Body> {
         let body = serde_json::to_string(&self).unwrap();
 
-        HttpResponse::build(self.status_code())
-            .content_type("application/json")
-            .body(body)
+        let mut res = HttpResponse::build(self.status_code());
+        if let Error::Overloaded = self {
+            // Queues drain quickly, so a short wait is usually enough.
+            res.insert_header((RETRY_AFTER, "1"));
+        }
+
+        res.content_type("application/json").body(body)
     }
 }
@@ -0,0 +1,71 @@
+use reqwest::Url;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::future::Future;
+use std::time::Duration;
+
+use super::variables::TRACE_HOSTS;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Upstream {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+}
+
+struct Context {
+    header: Option<String>,
+    upstream: RefCell<Vec<Upstream>>,
+}
+
+tokio::task_local! {
+    static CONTEXT: Context;
+}
+
+/// Run a request with the correlation header it arrived with,
+/// so that upstream fetches made on its behalf carry it along.
+pub async fn scope<F: Future>(header: Option<String>, fut: F) -> F::Output {
+    let context = Context {
+        header,
+        upstream: RefCell::new(Vec::new()),
+    };
+
+    CONTEXT.scope(context, fut).await
+}
+
+/// The correlation header of the current request, if it had one and
+/// `url` is on a host it may be passed to. Anywhere else it would only
+/// let third parties tie our fetches to the request behind them.
+pub fn header(url: &Url) -> Option<String> {
+    let host = url.host_str()?;
+    if !TRACE_HOSTS
+        .iter()
+        .any(|trusted| trusted.eq_ignore_ascii_case(host))
+    {
+        return None;
+    }
+
+    CONTEXT
+        .try_with(|context| context.header.clone())
+        .ok()
+        .flatten()
+}
+
+/// Note an upstream fetch made for the current request.
+pub fn record(url: &str, status: Option<u16>, latency: Duration) {
+    let _ = CONTEXT.try_with(|context| {
+        context.upstream.borrow_mut().push(Upstream {
+            url: url.to_string(),
+            status,
+            latency_ms: latency.as_millis() as u64,
+        })
+    });
+}
+
+/// Upstream fetches made so far for the current request.
+pub fn upstream() -> Vec<Upstream> {
+    CONTEXT
+        .try_with(|context| context.upstream.borrow().clone())
+        .unwrap_or_default()
+}
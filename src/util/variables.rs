@@ -1,7 +1,322 @@
+use actix_web::http::header::ContentEncoding;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
 use std::env;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+/// Host pattern whose embeddable iframe URL can be derived by template,
+/// `$1` and friends in `url` are replaced with the pattern's captures.
+#[derive(Deserialize)]
+pub struct IframeProvider {
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+    pub url: String,
+    pub width: isize,
+    pub height: isize,
+    #[serde(default = "default_sandbox")]
+    pub sandbox: String,
+    #[serde(default = "default_allow")]
+    pub allow: String,
+}
+
+fn default_sandbox() -> String {
+    "allow-scripts allow-same-origin allow-popups allow-presentation".to_string()
+}
+
+fn default_allow() -> String {
+    "autoplay; encrypted-media; fullscreen; picture-in-picture".to_string()
+}
+
+/// Where page metadata can be read from, tried in the order
+/// given by `JANUARY_META_PRECEDENCE`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetaSource {
+    OpenGraph,
+    Twitter,
+    Meta,
+    JsonLd,
+}
+
+fn meta_precedence(value: &str) -> Vec<MetaSource> {
+    value
+        .split(',')
+        .map(|source| match source.trim().to_lowercase().as_str() {
+            "opengraph" | "og" => MetaSource::OpenGraph,
+            "twitter" => MetaSource::Twitter,
+            "meta" => MetaSource::Meta,
+            "json-ld" | "jsonld" => MetaSource::JsonLd,
+            _ => panic!("Invalid JANUARY_META_PRECEDENCE."),
+        })
+        .collect()
+}
+
+/// Kinds of failure `JANUARY_FAULT_KINDS` can inject into upstream requests.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fault {
+    Latency,
+    Timeout,
+    Malformed,
+}
+
+fn fault_kinds(value: &str) -> Vec<Fault> {
+    value
+        .split(',')
+        .map(|kind| match kind.trim().to_lowercase().as_str() {
+            "latency" => Fault::Latency,
+            "timeout" => Fault::Timeout,
+            "malformed" => Fault::Malformed,
+            _ => panic!("Invalid JANUARY_FAULT_KINDS."),
+        })
+        .collect()
+}
+
+/// Read a comma separated list of hostnames.
+fn hosts(key: &str, default: &str) -> Vec<String> {
+    env::var(key)
+        .unwrap_or_else(|_| default.to_string())
+        .split(',')
+        .map(|host| host.trim().to_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect()
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
 
 lazy_static! {
     // Application Settings
     pub static ref HOST: String =
         env::var("JANUARY_HOST").expect("Missing JANUARY_HOST environment variable.");
+    pub static ref ADMIN_HOST: Option<String> = env::var("JANUARY_ADMIN_HOST").ok();
+    // Address clients reach this instance on, for links back to it inside embeds.
+    pub static ref PUBLIC_URL: Option<String> = env::var("JANUARY_PUBLIC_URL")
+        .ok()
+        .map(|v| v.trim_end_matches('/').to_string());
+    pub static ref FFMPEG: Option<String> = env::var("JANUARY_FFMPEG").ok();
+    // Defaults to the ffprobe installed alongside ffmpeg.
+    pub static ref FFPROBE: Option<String> = env::var("JANUARY_FFPROBE").ok().or_else(|| {
+        let ffmpeg = std::path::Path::new(FFMPEG.as_ref()?);
+        let name = ffmpeg.file_name()?.to_str()?.replacen("ffmpeg", "ffprobe", 1);
+        Some(ffmpeg.with_file_name(name).to_str()?.to_string())
+    });
+    pub static ref GIF_TRANSCODE_SIZE: usize = env::var("JANUARY_GIF_TRANSCODE_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1_000_000);
+    pub static ref MEDIA_CACHE_DIR: PathBuf = env::var("JANUARY_MEDIA_CACHE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| env::temp_dir().join("january"));
+    pub static ref MEDIA_CACHE_SIZE: u64 = env::var("JANUARY_MEDIA_CACHE_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1_000_000_000);
+    pub static ref MAX_TRANSCODES: usize = env::var("JANUARY_MAX_TRANSCODES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2);
+    pub static ref MAX_VIDEO_SIZE: usize = env::var("JANUARY_MAX_VIDEO_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(50_000_000);
+    pub static ref PREWARM_TOKEN: Option<String> = env::var("JANUARY_PREWARM_TOKEN").ok();
+    pub static ref ADMIN_TOKEN: Option<String> = env::var("JANUARY_ADMIN_TOKEN").ok();
+    // Upstream responses are replayed from, or recorded into, this directory.
+    pub static ref CASSETTE_DIR: Option<PathBuf> = env::var("JANUARY_CASSETTE_DIR").ok().map(PathBuf::from);
+    pub static ref CASSETTE_RECORD: bool = env::var("JANUARY_CASSETTE_RECORD")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(false);
+    pub static ref DAILY_QUOTA: Option<u32> = env::var("JANUARY_DAILY_QUOTA")
+        .ok()
+        .and_then(|v| v.parse().ok());
+    // Keys clients may present in `Authorization` to be counted apart from their address.
+    pub static ref API_KEYS: Vec<String> = env::var("JANUARY_API_KEYS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+        .collect();
+    // Proxies whose X-Forwarded-For is believed.
+    pub static ref TRUSTED_PROXIES: Vec<IpAddr> = env::var("JANUARY_TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|addr| addr.trim().parse().ok())
+        .collect();
+    pub static ref QUOTA_CLIENTS: usize = env::var("JANUARY_QUOTA_CLIENTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(100_000);
+    pub static ref DAILY_BYTE_QUOTA: Option<u64> = env::var("JANUARY_DAILY_BYTE_QUOTA")
+        .ok()
+        .and_then(|v| v.parse().ok());
+    pub static ref COMPRESSION: ContentEncoding = match env::var("JANUARY_COMPRESSION") {
+        Ok(v) if v != "auto" => ContentEncoding::from(v.as_str()),
+        _ => ContentEncoding::Auto,
+    };
+
+    // Connection Pool Settings
+    pub static ref POOL_MAX_IDLE_PER_HOST: usize = env::var("JANUARY_POOL_MAX_IDLE_PER_HOST")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(32);
+    pub static ref POOL_IDLE_TIMEOUT: u64 = env::var("JANUARY_POOL_IDLE_TIMEOUT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(90);
+    pub static ref TCP_KEEPALIVE: u64 = env::var("JANUARY_TCP_KEEPALIVE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(60);
+
+    // Limits
+    // Fetched by `january check` to test outbound connectivity.
+    pub static ref CHECK_URL: String =
+        env::var("JANUARY_CHECK_URL").unwrap_or_else(|_| "https://example.com/".to_string());
+    pub static ref MAX_CONCURRENT_REQUESTS: usize = env::var("JANUARY_MAX_CONCURRENT_REQUESTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(64);
+    pub static ref QUEUE_SIZE: usize = env::var("JANUARY_QUEUE_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(128);
+    pub static ref QUEUE_TIMEOUT: u64 = env::var("JANUARY_QUEUE_TIMEOUT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5);
+    pub static ref REQUEST_TIMEOUT: u64 = env::var("JANUARY_REQUEST_TIMEOUT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(15);
+    pub static ref MAX_EMBEDS_PER_REQUEST: usize = env::var("JANUARY_MAX_EMBEDS_PER_REQUEST")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5);
+    pub static ref OPT_OUT_WELL_KNOWN: bool = env::var("JANUARY_OPT_OUT_WELL_KNOWN")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(false);
+    pub static ref TRACE_HEADER: String =
+        env::var("JANUARY_TRACE_HEADER").unwrap_or_else(|_| "traceparent".to_string());
+    // Hosts of our own services, the only ones the correlation header is sent to.
+    pub static ref TRACE_HOSTS: Vec<String> = env::var("JANUARY_TRACE_HOSTS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .collect();
+    pub static ref MAX_DATA_URI_SIZE: usize = env::var("JANUARY_MAX_DATA_URI_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    pub static ref ALLOW_PRIVATE_ADDRESSES: bool = env::var("JANUARY_ALLOW_PRIVATE_ADDRESSES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(false);
+    pub static ref REPUTATION_HOOK: Option<String> = env::var("JANUARY_REPUTATION_HOOK").ok();
+    pub static ref PHISHING_BLOCKLIST: Option<PathBuf> =
+        env::var("JANUARY_PHISHING_BLOCKLIST").ok().map(PathBuf::from);
+    pub static ref MALWARE_BLOCKLIST: Option<PathBuf> =
+        env::var("JANUARY_MALWARE_BLOCKLIST").ok().map(PathBuf::from);
+    pub static ref REFUSE_DANGEROUS: bool = env::var("JANUARY_REFUSE_DANGEROUS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(false);
+    pub static ref MAX_URL_LENGTH: usize = env::var("JANUARY_MAX_URL_LENGTH")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2048);
+    pub static ref MAX_DOCUMENT_SIZE: usize = env::var("JANUARY_MAX_DOCUMENT_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(4_000_000);
+
+    // Cache Settings
+    pub static ref CACHE_TTL: u64 = env::var("JANUARY_CACHE_TTL")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(3600);
+    pub static ref WARNING_CACHE_TTL: u64 = env::var("JANUARY_WARNING_CACHE_TTL")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(60);
+    pub static ref DANGEROUS_CACHE_TTL: u64 = env::var("JANUARY_DANGEROUS_CACHE_TTL")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(300);
+    pub static ref EMBED_CACHE_SIZE: usize = env::var("JANUARY_EMBED_CACHE_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1024);
+    pub static ref PROXY_CACHE_SIZE: usize = env::var("JANUARY_PROXY_CACHE_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(128);
+}
+
+// Kept apart from the above, one block this size exceeds the macro recursion limit.
+lazy_static! {
+    // Bytes of images each of the proxy's caches keeps, besides their count.
+    pub static ref PROXY_CACHE_BYTES: usize = env::var("JANUARY_PROXY_CACHE_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(256_000_000);
+    pub static ref MAX_IMAGE_SIZE: usize = env::var("JANUARY_MAX_IMAGE_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(20_000_000);
+
+    // Provider Settings
+    pub static ref SONGLINK_RESOLVE: bool = env::var("JANUARY_SONGLINK_RESOLVE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(false);
+    pub static ref WAYBACK_FALLBACK: bool = env::var("JANUARY_WAYBACK_FALLBACK")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(false);
+    pub static ref NITTER_HOSTS: Vec<String> = hosts("JANUARY_NITTER_HOSTS", "nitter.net");
+    pub static ref INVIDIOUS_HOSTS: Vec<String> =
+        hosts("JANUARY_INVIDIOUS_HOSTS", "yewtu.be,invidious.snopyta.org");
+    pub static ref LIBREDDIT_HOSTS: Vec<String> =
+        hosts("JANUARY_LIBREDDIT_HOSTS", "libredd.it,teddit.net");
+    pub static ref REFERER_HOSTS: Vec<String> = hosts("JANUARY_REFERER_HOSTS", "");
+    pub static ref STOCK_EMBEDS: bool = env::var("JANUARY_STOCK_EMBEDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(false);
+    pub static ref OPENWEATHERMAP_KEY: Option<String> = env::var("JANUARY_OPENWEATHERMAP_KEY").ok();
+    pub static ref STATIC_MAP_URL: Option<String> = env::var("JANUARY_STATIC_MAP_URL").ok();
+    pub static ref MATRIX_HOMESERVER: String = env::var("JANUARY_MATRIX_HOMESERVER")
+        .unwrap_or_else(|_| "https://matrix.org".to_string());
+    pub static ref REVOLT_APP_URL: Option<String> = env::var("JANUARY_REVOLT_APP_URL").ok();
+    pub static ref REVOLT_API_URL: Option<String> = env::var("JANUARY_REVOLT_API_URL").ok();
+    pub static ref REVOLT_AUTUMN_URL: Option<String> = env::var("JANUARY_REVOLT_AUTUMN_URL").ok();
+    pub static ref REVOLT_BOT_TOKEN: Option<String> = env::var("JANUARY_REVOLT_BOT_TOKEN").ok();
+    pub static ref FACEBOOK_TOKEN: Option<String> = env::var("JANUARY_FACEBOOK_TOKEN").ok();
+    pub static ref GITHUB_TOKEN: Option<String> = env::var("JANUARY_GITHUB_TOKEN").ok();
+    pub static ref IMGUR_CLIENT_ID: Option<String> = env::var("JANUARY_IMGUR_CLIENT_ID").ok();
+    // Failure injection, for exercising error handling in staging.
+    pub static ref FAULT_RATE: f64 = env::var("JANUARY_FAULT_RATE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|rate: &f64| rate.is_finite())
+        .unwrap_or(0.0);
+    pub static ref FAULT_LATENCY: u64 = env::var("JANUARY_FAULT_LATENCY")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(3000);
+    pub static ref FAULT_KINDS: Vec<Fault> = fault_kinds(
+        &env::var("JANUARY_FAULT_KINDS").unwrap_or_else(|_| "latency,timeout,malformed".to_string())
+    );
+    pub static ref META_PRECEDENCE: Vec<MetaSource> = meta_precedence(
+        &env::var("JANUARY_META_PRECEDENCE").unwrap_or_else(|_| "opengraph,twitter,meta,json-ld".to_string())
+    );
+    pub static ref IFRAME_PROVIDERS: Vec<IframeProvider> = env::var("JANUARY_IFRAME_PROVIDERS")
+        .ok()
+        .map(|v| serde_json::from_str(&v).expect("Invalid JANUARY_IFRAME_PROVIDERS."))
+        .unwrap_or_default();
 }
@@ -0,0 +1,11 @@
+//! Embeds as January serves them, for clients which would rather not
+//! write their own structs. Every type deserializes from the JSON the
+//! server produces and serializes back to the same form.
+
+pub mod media;
+pub mod metadata;
+pub mod special;
+
+pub use media::{Image, ImageSize, Video};
+pub use metadata::{Metadata, Snapshot, Threat, Warning};
+pub use special::Special;
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::media::{Image, Video};
+use super::special::Special;
+
+/// Why a link was flagged, for clients to word their warning.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Threat {
+    Phishing,
+    Malware,
+}
+
+/// Wayback Machine snapshot an embed was built from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub url: String,
+    pub timestamp: String,
+}
+
+/// Part of an embed which could not be resolved, so clients
+/// can tell a degraded embed apart and decide whether to retry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Warning {
+    pub field: String,
+    // The error as the server reports it, tagged by `type`.
+    pub error: Value,
+}
+
+/// A website embed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    pub url: String,
+    pub special: Option<Special>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<Image>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video: Option<Video>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opengraph_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub determiner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colour: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived: Option<Snapshot>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+    // Flagged by a reputation check, so nothing was fetched.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dangerous: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<Threat>,
+}
@@ -0,0 +1,535 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TwitchType {
+    Channel,
+    Video,
+    Clip,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BandcampType {
+    Album,
+    Track,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AppleMusicType {
+    Album,
+    Song,
+    Playlist,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RevoltType {
+    Invite,
+    Channel,
+    User,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FacebookType {
+    Video,
+    Post,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ArtworkProvider {
+    DeviantArt,
+    ArtStation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PhotoProvider {
+    Flickr,
+    Unsplash,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SlidesProvider {
+    SpeakerDeck,
+    SlideShare,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FileProvider {
+    Dropbox,
+    GoogleDrive,
+    OneDrive,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PasteProvider {
+    Pastebin,
+    Gist,
+    Hastebin,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CampaignProvider {
+    Kickstarter,
+    Patreon,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GitHubType {
+    Repository,
+    Issue,
+    PullRequest,
+    Gist,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ImgurType {
+    Album,
+    Gallery,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RedditType {
+    Post,
+    Comment,
+    Subreddit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GifProvider {
+    Giphy,
+    Tenor,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Special {
+    None,
+    YouTube {
+        id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timestamp: Option<String>,
+    },
+    Vimeo {
+        id: String,
+
+        // Unlisted videos can only be played with their hash.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        hash: Option<String>,
+    },
+    Dailymotion {
+        id: String,
+    },
+    Twitch {
+        content_type: TwitchType,
+        id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        live: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        thumbnail: Option<String>,
+    },
+    Spotify {
+        content_type: String,
+        id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        artwork: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        colour: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        links: Option<BTreeMap<String, String>>,
+    },
+    Deezer {
+        content_type: String,
+        id: String,
+    },
+    Tidal {
+        content_type: String,
+        id: String,
+    },
+    AppleMusic {
+        storefront: String,
+        content_type: AppleMusicType,
+        id: String,
+    },
+    Mixcloud {
+        user: String,
+        slug: String,
+    },
+    Soundcloud {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        secret_token: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        links: Option<BTreeMap<String, String>>,
+    },
+    Songlink {
+        links: BTreeMap<String, String>,
+    },
+    Bandcamp {
+        content_type: BandcampType,
+        id: String,
+    },
+    Iframe {
+        url: String,
+        width: isize,
+        height: isize,
+
+        // Recommended attributes for the client's <iframe>.
+        sandbox: String,
+        allow: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        aspect_ratio: Option<f64>,
+    },
+    Gif {
+        provider: GifProvider,
+        id: String,
+        width: isize,
+        height: isize,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        preview: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        gif: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        mp4: Option<String>,
+    },
+    Fediverse {
+        id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author_url: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author_name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author_avatar: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        content: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        content_warning: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        published: Option<String>,
+        sensitive: bool,
+        attachments: Vec<String>,
+    },
+    Mastodon {
+        id: String,
+        instance: String,
+        author: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author_name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author_url: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author_avatar: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        content: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        content_warning: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        published: Option<String>,
+        sensitive: bool,
+        attachments: Vec<String>,
+    },
+    Matrix {
+        room_id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        alias: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        event_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        topic: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        avatar: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        members: Option<usize>,
+    },
+    DiscordInvite {
+        code: String,
+        name: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        icon: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        members: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        online: Option<usize>,
+    },
+    Revolt {
+        content_type: RevoltType,
+        id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        icon: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        member_count: Option<usize>,
+    },
+    Instagram {
+        id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        caption: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        image: Option<String>,
+    },
+    Twitter {
+        user: String,
+        id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+    },
+    Tumblr {
+        blog: String,
+        id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        summary: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        image: Option<String>,
+    },
+    Bluesky {
+        did: String,
+        rkey: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        handle: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author_name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+        images: Vec<String>,
+    },
+    GitHub {
+        content_type: GitHubType,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        owner: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        repo: Option<String>,
+        // Issue or pull request number, or the gist's id.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        state: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        language: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        stars: Option<u64>,
+    },
+    GitLab {
+        instance: String,
+        path: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        default_branch: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        stars: Option<u64>,
+    },
+    Steam {
+        app_id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        price: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        release_date: Option<String>,
+        coming_soon: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        header_image: Option<String>,
+    },
+    Wikipedia {
+        language: String,
+        title: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        extract: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        thumbnail: Option<String>,
+    },
+    Imgur {
+        content_type: ImgurType,
+        id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        count: Option<usize>,
+    },
+    Reddit {
+        content_type: RedditType,
+        subreddit: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author: Option<String>,
+        // For posts and comments.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        score: Option<i64>,
+        // For subreddits.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        subscribers: Option<i64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        image: Option<String>,
+    },
+    TikTok {
+        id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        caption: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        thumbnail: Option<String>,
+    },
+    Facebook {
+        content_type: FacebookType,
+        id: String,
+    },
+    Artwork {
+        provider: ArtworkProvider,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        image: Option<String>,
+        mature: bool,
+    },
+    Photo {
+        provider: PhotoProvider,
+        image: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        thumbnail: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        width: Option<isize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        height: Option<isize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author_url: Option<String>,
+    },
+    TwitchCategory {
+        id: String,
+        name: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        box_art: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        channels: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        viewers: Option<usize>,
+    },
+    Slides {
+        provider: SlidesProvider,
+        id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pages: Option<usize>,
+    },
+    InternetArchive {
+        id: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        creator: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        media_type: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        player: Option<String>,
+    },
+    File {
+        provider: FileProvider,
+        name: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        mime: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        size: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        thumbnail: Option<String>,
+    },
+    Paste {
+        provider: PasteProvider,
+        content: String,
+        truncated: bool,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        language: Option<String>,
+    },
+    Itch {
+        id: String,
+        platforms: Vec<String>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        price: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cover: Option<String>,
+    },
+    Campaign {
+        provider: CampaignProvider,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        supporters: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pledged: Option<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        goal: Option<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        currency: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        state: Option<String>,
+    },
+    Stock {
+        symbol: String,
+        price: f64,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        currency: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        change: Option<f64>,
+    },
+    Weather {
+        city: String,
+        // Degrees Celsius.
+        temperature: f64,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        icon: Option<String>,
+    },
+}